@@ -1,8 +1,142 @@
 use crate::expr::*;
+use crate::stmt::*;
 use crate::token::*;
+use std::rc::Rc;
 
 pub struct Ast {
-    expression: Expr,
+    statements: Vec<Stmt>,
+}
+
+impl StmtVisitor for Ast {
+    type Result = String;
+
+    fn visit_stmt(&self, stmt: &Stmt) -> Self::Result {
+        match stmt {
+            Stmt::Block(block) => self.visit_block_stmt(block),
+            Stmt::Class(class) => self.visit_class_stmt(class),
+            Stmt::Expression(expression) => self.visit_expression_stmt(expression),
+            Stmt::Function(function) => self.visit_function_stmt(function),
+            Stmt::If(if_stmt) => self.visit_if_stmt(if_stmt),
+            Stmt::Print(print) => self.visit_print_stmt(print),
+            Stmt::Return(return_stmt) => self.visit_return_stmt(return_stmt),
+            Stmt::Var(var) => self.visit_var_stmt(var),
+            Stmt::While(while_stmt) => self.visit_while_stmt(while_stmt),
+        }
+    }
+
+    fn visit_block_stmt(&self, stmt: &Block) -> String {
+        let mut result = String::new();
+
+        result.push_str("(block");
+
+        for statement in &stmt.statements {
+            result.push(' ');
+            result.push_str(&statement.accept(self));
+        }
+
+        result.push(')');
+        result
+    }
+
+    fn visit_class_stmt(&self, stmt: &Class) -> String {
+        let mut result = String::new();
+
+        result.push_str("(class ");
+        result.push_str(&stmt.name.lexeme);
+
+        if let Some(superclass) = &stmt.superclass {
+            result.push_str(" < ");
+            result.push_str(&superclass.name.lexeme);
+        }
+
+        for method in &stmt.methods {
+            result.push(' ');
+            result.push_str(&self.visit_function_stmt(method));
+        }
+
+        result.push(')');
+        result
+    }
+
+    fn visit_expression_stmt(&self, stmt: &Expression) -> String {
+        let mut result = String::new();
+
+        result.push_str("(; ");
+        result.push_str(&stmt.expression.accept(self));
+        result.push(')');
+        result
+    }
+
+    fn visit_function_stmt(&self, stmt: &Rc<Function>) -> String {
+        self.function(&stmt.name.lexeme, &stmt.params, &stmt.body)
+    }
+
+    fn visit_if_stmt(&self, stmt: &If) -> String {
+        let mut result = String::new();
+
+        result.push_str("(if ");
+        result.push_str(&stmt.condition.accept(self));
+        result.push(' ');
+        result.push_str(&stmt.then_branch.accept(self));
+
+        if let Some(else_branch) = &stmt.else_branch {
+            result.push(' ');
+            result.push_str(&else_branch.accept(self));
+        }
+
+        result.push(')');
+        result
+    }
+
+    fn visit_print_stmt(&self, stmt: &Print) -> String {
+        let mut result = String::new();
+
+        result.push_str("(print ");
+        result.push_str(&stmt.expression.accept(self));
+        result.push(')');
+        result
+    }
+
+    fn visit_return_stmt(&self, stmt: &Return) -> String {
+        let mut result = String::new();
+
+        result.push('(');
+        result.push_str(&stmt.keyword.lexeme);
+
+        if let Some(value) = &stmt.value {
+            result.push(' ');
+            result.push_str(&value.accept(self));
+        }
+
+        result.push(')');
+        result
+    }
+
+    fn visit_var_stmt(&self, stmt: &Var) -> String {
+        let mut result = String::new();
+
+        result.push_str("(var ");
+        result.push_str(&stmt.name.lexeme);
+
+        if let Some(initializer) = &stmt.initializer {
+            result.push_str(" = ");
+            result.push_str(&initializer.accept(self));
+        }
+
+        result.push(')');
+        result
+    }
+
+    fn visit_while_stmt(&self, stmt: &While) -> String {
+        let mut result = String::new();
+
+        result.push_str("(while ");
+        result.push_str(&stmt.condition.accept(self));
+        result.push(' ');
+        result.push_str(&stmt.body.accept(self));
+        result.push(')');
+        result
+    }
 }
 
 impl Visitor for Ast {
@@ -10,132 +144,305 @@ impl Visitor for Ast {
 
     fn visit_expr(&self, expr: &Expr) -> Self::Result {
         match expr {
+            Expr::Assign(assign) => self.visit_assign_expr(assign),
             Expr::Binary(binary) => self.visit_binary_expr(binary),
+            Expr::Call(call) => self.visit_call_expr(call),
+            Expr::Comma(comma) => self.visit_comma_expr(comma),
+            Expr::Conditional(conditional) => self.visit_conditional_expr(conditional),
+            Expr::Get(get) => self.visit_get_expr(get),
             Expr::Grouping(grouping) => self.visit_grouping_expr(grouping),
+            Expr::Index(index) => self.visit_index_expr(index),
+            Expr::IndexSet(index_set) => self.visit_index_set_expr(index_set),
+            Expr::Interpolation(interpolation) => self.visit_interpolation_expr(interpolation),
+            Expr::Lambda(lambda) => self.visit_lambda_expr(lambda),
+            Expr::List(list) => self.visit_list_expr(list),
             Expr::Literal(literal) => self.visit_literal_expr(literal),
+            Expr::Logical(logical) => self.visit_logical_expr(logical),
+            Expr::Map(map) => self.visit_map_expr(map),
+            Expr::Set(set) => self.visit_set_expr(set),
+            Expr::Super(super_expr) => self.visit_super_expr(super_expr),
+            Expr::This(this) => self.visit_this_expr(this),
             Expr::Unary(unary) => self.visit_unary_expr(unary),
+            Expr::Variable(variable) => self.visit_variable_expr(variable),
         }
     }
 
+    fn visit_assign_expr(&self, expr: &Assign) -> String {
+        let mut result = String::new();
+
+        result.push_str("(= ");
+        result.push_str(&expr.name.lexeme);
+        result.push(' ');
+        result.push_str(&expr.value.accept(self));
+        result.push(')');
+        result
+    }
+
     fn visit_binary_expr(&self, expr: &Binary) -> String {
         let mut result = String::new();
-        let binary_expr = &*expr;
-        let operator_lexeme = get_token_lexeme(&binary_expr.operator);
+        let operator_lexeme = &expr.operator.lexeme;
 
         result.push('(');
         result.push_str(operator_lexeme);
-        result.push_str(" ");
-        result.push_str(&self.visit_expr(&binary_expr.left));
-        result.push_str(" ");
-        result.push_str(&self.visit_expr(&binary_expr.right));
+        result.push(' ');
+        result.push_str(&self.visit_expr(&expr.left));
+        result.push(' ');
+        result.push_str(&self.visit_expr(&expr.right));
+        result.push(')');
+
+        result
+    }
+
+    fn visit_call_expr(&self, expr: &Call) -> String {
+        let mut result = String::new();
+
+        result.push_str("(call ");
+        result.push_str(&expr.callee.accept(self));
+
+        for argument in &expr.arguments {
+            result.push(' ');
+            result.push_str(&argument.accept(self));
+        }
+
+        result.push(')');
+        result
+    }
+
+    fn visit_comma_expr(&self, expr: &Comma) -> String {
+        let mut result = String::new();
+
+        result.push_str("(, ");
+        result.push_str(&expr.left.accept(self));
+        result.push(' ');
+        result.push_str(&expr.right.accept(self));
+        result.push(')');
+        result
+    }
+
+    fn visit_conditional_expr(&self, expr: &Conditional) -> String {
+        let mut result = String::new();
+
+        result.push_str("(?: ");
+        result.push_str(&expr.condition.accept(self));
+        result.push(' ');
+        result.push_str(&expr.then_branch.accept(self));
+        result.push(' ');
+        result.push_str(&expr.else_branch.accept(self));
         result.push(')');
+        result
+    }
 
+    fn visit_get_expr(&self, expr: &Get) -> String {
+        let mut result = String::new();
+
+        result.push_str("(. ");
+        result.push_str(&expr.object.accept(self));
+        result.push(' ');
+        result.push_str(&expr.name.lexeme);
+        result.push(')');
         result
     }
 
     fn visit_grouping_expr(&self, expr: &Grouping) -> String {
         let mut result = String::new();
-        let grouping_expr = &*expr;
-        result.push_str("(");
-        result.push_str(&grouping_expr.expression.accept(self));
-        result.push_str(")");
+        result.push('(');
+        result.push_str(&expr.expression.accept(self));
+        result.push(')');
         result
     }
 
-    fn visit_literal_expr(&self, expr: &Literal) -> String {
+    fn visit_index_expr(&self, expr: &Index) -> String {
+        let mut result = String::new();
+
+        result.push_str("([] ");
+        result.push_str(&expr.object.accept(self));
+        result.push(' ');
+        result.push_str(&expr.index.accept(self));
+        result.push(')');
+        result
+    }
+
+    fn visit_index_set_expr(&self, expr: &IndexSet) -> String {
+        let mut result = String::new();
+
+        result.push_str("(= ([] ");
+        result.push_str(&expr.object.accept(self));
+        result.push(' ');
+        result.push_str(&expr.index.accept(self));
+        result.push_str(") ");
+        result.push_str(&expr.value.accept(self));
+        result.push(')');
+        result
+    }
+
+    fn visit_interpolation_expr(&self, expr: &Interpolation) -> String {
+        let mut result = String::new();
+
+        result.push_str("(interpolate");
+
+        for part in &expr.parts {
+            result.push(' ');
+            result.push_str(&part.accept(self));
+        }
+
+        result.push(')');
+        result
+    }
+
+    fn visit_lambda_expr(&self, expr: &Rc<Lambda>) -> String {
+        self.function("", &expr.params, &expr.body)
+    }
+
+    fn visit_list_expr(&self, expr: &List) -> String {
         let mut result = String::new();
-        let literal_expr = &*expr;
 
-        let value = match literal_expr {
-            Literal::Number(number) => number.to_string(),
-            Literal::String(string) => string.clone(),
-            Literal::Boolean(boolean) => boolean.to_string(),
-            Literal::Nil => "nil".to_string(),
+        result.push_str("(list");
+
+        for element in &expr.elements {
+            result.push(' ');
+            result.push_str(&element.accept(self));
+        }
+
+        result.push(')');
+        result
+    }
+
+    fn visit_literal_expr(&self, expr: &Literal) -> String {
+        let mut result = String::new();
+        let value = match &expr.value {
+            LiteralValue::Number(number) => number.to_string(),
+            LiteralValue::String(string) => format!("{string:?}"),
+            LiteralValue::Boolean(boolean) => boolean.to_string(),
+            LiteralValue::Nil => "nil".to_string(),
         };
 
         result.push_str(&value);
         result
     }
 
+    fn visit_logical_expr(&self, expr: &Logical) -> String {
+        let mut result = String::new();
+        let operator_lexeme = &expr.operator.lexeme;
+
+        result.push('(');
+        result.push_str(operator_lexeme);
+        result.push(' ');
+        result.push_str(&expr.left.accept(self));
+        result.push(' ');
+        result.push_str(&expr.right.accept(self));
+        result.push(')');
+        result
+    }
+
+    fn visit_map_expr(&self, expr: &Map) -> String {
+        let mut result = String::new();
+
+        result.push_str("(map");
+
+        for entry in &expr.entries {
+            result.push_str(" (: ");
+            result.push_str(&entry.key.accept(self));
+            result.push(' ');
+            result.push_str(&entry.value.accept(self));
+            result.push(')');
+        }
+
+        result.push(')');
+        result
+    }
+
+    fn visit_set_expr(&self, expr: &Set) -> String {
+        let mut result = String::new();
+
+        result.push_str("(= (. ");
+        result.push_str(&expr.object.accept(self));
+        result.push(' ');
+        result.push_str(&expr.name.lexeme);
+        result.push_str(") ");
+        result.push_str(&expr.value.accept(self));
+        result.push(')');
+        result
+    }
+
+    fn visit_super_expr(&self, expr: &Super) -> String {
+        let mut result = String::new();
+
+        result.push_str("(. super ");
+        result.push_str(&expr.method.lexeme);
+        result.push(')');
+        result
+    }
+
+    fn visit_this_expr(&self, expr: &This) -> String {
+        expr.keyword.lexeme.clone()
+    }
+
     fn visit_unary_expr(&self, expr: &Unary) -> String {
         let mut result = String::new();
-        let unary_expr = &*expr;
-        let operator_lexeme = get_token_lexeme(&unary_expr.operator);
+        let operator_lexeme = &expr.operator.lexeme;
 
         result.push_str(operator_lexeme);
-        result.push_str(&unary_expr.right.accept(self));
+        result.push_str(&expr.right.accept(self));
         result
     }
+
+    fn visit_variable_expr(&self, expr: &Variable) -> String {
+        expr.name.lexeme.clone()
+    }
 }
 
 impl Ast {
-    pub fn new(expression: Expr) -> Self {
-        Self { expression }
+    pub fn new(statements: Vec<Stmt>) -> Self {
+        Self { statements }
     }
     pub fn print(&self) {
-        let result = self.visit_expr(&self.expression);
-        println!("{}", result);
+        for statement in &self.statements {
+            let result = statement.accept(self);
+            println!("{}", result);
+        }
     }
+    // Shared by declarations and lambdas, which just leave the name empty.
+    fn function(&self, name: &str, params: &[Token], body: &[Stmt]) -> String {
+        let mut result = String::new();
+        let params: Vec<&str> = params.iter().map(|param| param.lexeme.as_str()).collect();
+
+        result.push_str("(fun ");
+        result.push_str(name);
+        result.push('(');
+        result.push_str(&params.join(" "));
+        result.push(')');
+
+        for statement in body {
+            result.push(' ');
+            result.push_str(&statement.accept(self));
+        }
+
+        result.push(')');
+        result
+    }
+    #[allow(dead_code)]
     pub fn new_test_ast_to_str(&self) {
+        let span = Span::default();
+        let number = |value| {
+            Expr::Literal(Literal {
+                value: LiteralValue::Number(value),
+                span,
+            })
+        };
         let expression = Expr::Binary(Box::new(Binary {
             left: Expr::Binary(Box::new(Binary {
-                left: Expr::Literal(Literal::Number(1.0)),
-                operator: Token::Plus("+", "+", 1),
-                right: Expr::Literal(Literal::Number(2.0)),
+                left: number(1.0),
+                operator: Token::symbol("+", span),
+                right: number(2.0),
             })),
             right: Expr::Binary(Box::new(Binary {
-                left: Expr::Literal(Literal::Number(1.0)),
-                operator: Token::Plus("+", "+", 1),
-                right: Expr::Literal(Literal::Number(2.0)),
+                left: number(1.0),
+                operator: Token::symbol("+", span),
+                right: number(2.0),
             })),
-            operator: Token::Minus("-", "-", 1),
+            operator: Token::symbol("-", span),
         }));
-        let ast = Ast::new(expression);
+        let ast = Ast::new(vec![Stmt::Expression(Expression { expression })]);
         ast.print();
     }
 }
-
-fn get_token_lexeme(token: &Token) -> &str {
-    match token {
-        Token::And(lexeme, _, _) => *lexeme,
-        Token::Bang(lexeme, _, _) => *lexeme,
-        Token::BangEqual(lexeme, _, _) => *lexeme,
-        Token::Class(lexeme, _, _) => *lexeme,
-        Token::Comma(lexeme, _, _) => *lexeme,
-        Token::Dot(lexeme, _, _) => *lexeme,
-        Token::Else(lexeme, _, _) => *lexeme,
-        Token::Equal(lexeme, _, _) => *lexeme,
-        Token::EqualEqual(lexeme, _, _) => *lexeme,
-        Token::False(lexeme, _, _) => *lexeme,
-        Token::For(lexeme, _, _) => *lexeme,
-        Token::Fun(lexeme, _, _) => *lexeme,
-        Token::Greater(lexeme, _, _) => *lexeme,
-        Token::GreaterEqual(lexeme, _, _) => *lexeme,
-        Token::Identifier(lexeme, _, _) => lexeme,
-        Token::If(lexeme, _, _) => *lexeme,
-        Token::LeftBracket(lexeme, _, _) => *lexeme,
-        Token::LeftBrace(lexeme, _, _) => *lexeme,
-        Token::LeftParen(lexeme, _, _) => *lexeme,
-        Token::Less(lexeme, _, _) => *lexeme,
-        Token::LessEqual(lexeme, _, _) => *lexeme,
-        Token::Nil(lexeme, _, _) => *lexeme,
-        Token::Number(lexeme, _, _) => lexeme,
-        Token::Or(lexeme, _, _) => *lexeme,
-        Token::Minus(lexeme, _, _) => *lexeme,
-        Token::Plus(lexeme, _, _) => *lexeme,
-        Token::Print(lexeme, _, _) => *lexeme,
-        Token::Return(lexeme, _, _) => *lexeme,
-        Token::RightParen(lexeme, _, _) => *lexeme,
-        Token::RightBracket(lexeme, _, _) => *lexeme,
-        Token::RightBrace(lexeme, _, _) => *lexeme,
-        Token::Semicolon(lexeme, _, _) => *lexeme,
-        Token::Slash(lexeme, _, _) => *lexeme,
-        Token::Star(lexeme, _, _) => *lexeme,
-        Token::String(lexeme, _, _) => lexeme,
-        Token::Super(lexeme, _, _) => *lexeme,
-        Token::This(lexeme, _, _) => *lexeme,
-        Token::True(lexeme, _, _) => *lexeme,
-        Token::Var(lexeme, _, _) => *lexeme,
-        Token::While(lexeme, _, _) => *lexeme,
-    }
-}
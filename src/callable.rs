@@ -0,0 +1,150 @@
+use crate::environment::Environment;
+use crate::expr::Lambda;
+use crate::interpreter::{Interpreter, RuntimeError, Unwind};
+use crate::stmt::{Function, Stmt};
+use crate::token::Token;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+pub trait Callable {
+    fn arity(&self) -> usize;
+    // `paren` is the closing parenthesis of the call, used to report errors at the call site.
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError>;
+}
+
+// Where a function's parameters and body come from. Only declarations and
+// methods have a name, a lambda is just the parameters and body.
+#[derive(Clone)]
+enum Declaration {
+    Function(Rc<Function>),
+    Lambda(Rc<Lambda>),
+}
+
+impl Declaration {
+    fn params(&self) -> &[Token] {
+        match self {
+            Declaration::Function(function) => &function.params,
+            Declaration::Lambda(lambda) => &lambda.params,
+        }
+    }
+    fn body(&self) -> &[Stmt] {
+        match self {
+            Declaration::Function(function) => &function.body,
+            Declaration::Lambda(lambda) => &lambda.body,
+        }
+    }
+}
+
+pub struct LoxFunction {
+    declaration: Declaration,
+    closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: Rc<Function>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            declaration: Declaration::Function(declaration),
+            closure,
+            is_initializer,
+        }
+    }
+    pub fn lambda(lambda: Rc<Lambda>, closure: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            declaration: Declaration::Lambda(lambda),
+            closure,
+            is_initializer: false,
+        }
+    }
+    // Wraps the closure in a scope where `this` is the given instance.
+    pub fn bind(&self, instance: Value) -> LoxFunction {
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.closure));
+
+        environment.define("this".to_owned(), instance);
+
+        LoxFunction {
+            declaration: self.declaration.clone(),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
+        }
+    }
+    fn this(&self) -> Value {
+        self.closure
+            .borrow()
+            .get_local("this")
+            .unwrap_or(Value::Nil)
+    }
+}
+
+impl Callable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params().len()
+    }
+    fn call(
+        &self,
+        interpreter: &Interpreter,
+        _paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        // every call gets a fresh scope so recursion doesn't clobber parameters
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.closure));
+
+        for (param, argument) in self.declaration.params().iter().zip(arguments) {
+            environment.define(param.lexeme.clone(), argument);
+        }
+
+        match interpreter.execute_block(self.declaration.body(), environment) {
+            // an initializer always hands back the instance, even on an early `return;`
+            Ok(()) | Err(Unwind::Return(_)) if self.is_initializer => Ok(self.this()),
+            Ok(()) => Ok(Value::Nil),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(error)) => Err(error),
+        }
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.declaration {
+            Declaration::Function(function) => write!(f, "<fn {}>", function.name.lexeme),
+            Declaration::Lambda(_) => write!(f, "<fn>"),
+        }
+    }
+}
+
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: usize,
+    pub function: fn(&Token, &[Value]) -> Result<Value, RuntimeError>,
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+    fn call(
+        &self,
+        _interpreter: &Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        (self.function)(paren, &arguments)
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
@@ -0,0 +1,157 @@
+use crate::interpreter::RuntimeError;
+use crate::parser::ParseError;
+use crate::resolver::ResolveError;
+use crate::scanner::ScanError;
+use crate::token::Span;
+use std::env;
+use std::io::{self, IsTerminal};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// Whatever went wrong, boiled down to what we need to point at it in the source.
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+    pub help: Option<&'static str>,
+}
+
+impl From<ScanError> for Diagnostic {
+    fn from(error: ScanError) -> Self {
+        Diagnostic {
+            message: error.message,
+            span: error.span,
+            help: error.help,
+        }
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Self {
+        Diagnostic {
+            message: error.message,
+            span: error.span,
+            help: error.help,
+        }
+    }
+}
+
+impl From<ResolveError> for Diagnostic {
+    fn from(error: ResolveError) -> Self {
+        Diagnostic {
+            message: error.message,
+            span: error.span,
+            help: error.help,
+        }
+    }
+}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(error: RuntimeError) -> Self {
+        Diagnostic {
+            message: error.message,
+            span: error.span,
+            help: error.help,
+        }
+    }
+}
+
+// Prints errors rustc-style, with the offending line and the span underlined:
+//
+// error: Undefined variable 'b'.
+//  --> script.lox:2:7
+//   |
+// 2 | print b;
+//   |       ^
+//   |
+//   = help: declare it with 'var' before using it
+pub struct Reporter<'a> {
+    file_name: &'a str,
+    source: &'a str,
+    color: bool,
+}
+
+impl<'a> Reporter<'a> {
+    pub fn new(file_name: &'a str, source: &'a str) -> Self {
+        // diagnostics go to stderr, so that is the stream that decides
+        let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+
+        Reporter {
+            file_name,
+            source,
+            color,
+        }
+    }
+
+    pub fn report(&self, diagnostic: impl Into<Diagnostic>) {
+        eprint!("{}", self.render(&diagnostic.into()));
+    }
+
+    fn render(&self, diagnostic: &Diagnostic) -> String {
+        let span = diagnostic.span;
+        let line = self.source.lines().nth(span.line - 1).unwrap_or("");
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let gutter = " ".repeat(span.line.to_string().len());
+
+        let mut output = format!(
+            "{}: {}\n",
+            self.paint(RED, "error"),
+            self.paint(BOLD, &diagnostic.message)
+        );
+
+        output += &format!(
+            "{gutter}{} {}:{}:{}\n",
+            self.paint(BLUE, "-->"),
+            self.file_name,
+            span.line,
+            span.column
+        );
+        output += &format!("{gutter} {}\n", self.paint(BLUE, "|"));
+        output += &format!("{} {line}\n", self.paint(BLUE, &format!("{} |", span.line)));
+        output += &format!(
+            "{gutter} {} {}{}\n",
+            self.paint(BLUE, "|"),
+            self.indent(line, span.column),
+            self.paint(RED, &"^".repeat(self.width(span)))
+        );
+
+        if let Some(help) = &diagnostic.help {
+            output += &format!("{gutter} {}\n", self.paint(BLUE, "|"));
+            output += &format!(
+                "{gutter} {} {}: {help}\n",
+                self.paint(BLUE, "="),
+                self.paint(BOLD, "help")
+            );
+        }
+
+        output
+    }
+
+    // Copies tabs from the source line so the carets stay lined up under them.
+    fn indent(&self, line: &str, column: usize) -> String {
+        line.chars()
+            .chain(std::iter::repeat(' '))
+            .take(column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect()
+    }
+
+    // How many chars to underline. Spans running over several lines are cut
+    // at the end of the first one, and empty spans still get a single caret.
+    fn width(&self, span: Span) -> usize {
+        let text = self.source.get(span.start..span.end).unwrap_or("");
+        let text = text.split('\n').next().unwrap_or("");
+
+        text.chars().count().max(1)
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{color}{text}{RESET}")
+        } else {
+            text.to_owned()
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::interpreter::RuntimeError;
+use crate::token::Token;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: None,
+        }
+    }
+    pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+    // Redefining an existing name is allowed, it simply shadows the old value.
+    pub fn define(&mut self, name: String, value: Value) {
+        self.values.insert(name, value);
+    }
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        let lexeme = &name.lexeme;
+
+        if let Some(value) = self.values.get(lexeme) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(undefined_variable(name)),
+        }
+    }
+    // Only looks at this scope, without walking up the enclosing ones.
+    pub fn get_local(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned()
+    }
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        let lexeme = &name.lexeme;
+
+        if let Some(slot) = self.values.get_mut(lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(undefined_variable(name)),
+        }
+    }
+    // Resolved lookups: the resolver already knows which scope holds the
+    // variable, so go straight there instead of searching outwards.
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Value, RuntimeError> {
+        if distance > 0 {
+            return match &self.enclosing {
+                Some(enclosing) => enclosing.borrow().get_at(distance - 1, name),
+                None => Err(undefined_variable(name)),
+            };
+        }
+
+        let lexeme = &name.lexeme;
+
+        self.values
+            .get(lexeme)
+            .cloned()
+            .ok_or_else(|| undefined_variable(name))
+    }
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        value: Value,
+    ) -> Result<(), RuntimeError> {
+        if distance > 0 {
+            return match &self.enclosing {
+                Some(enclosing) => enclosing.borrow_mut().assign_at(distance - 1, name, value),
+                None => Err(undefined_variable(name)),
+            };
+        }
+
+        let lexeme = &name.lexeme;
+
+        match self.values.get_mut(lexeme) {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => Err(undefined_variable(name)),
+        }
+    }
+}
+
+fn undefined_variable(name: &Token) -> RuntimeError {
+    RuntimeError::new(name, format!("Undefined variable '{}'.", name.lexeme))
+        .with_help("declare it with 'var' before using it")
+}
@@ -1,4 +1,7 @@
-use crate::token::Token;
+use crate::stmt::Stmt;
+use crate::token::{Span, Token};
+use std::cell::Cell;
+use std::rc::Rc;
 
 pub trait Acceptor {
     fn accept<V: Visitor>(&self, visitor: &V) -> V::Result;
@@ -7,19 +10,51 @@ pub trait Acceptor {
 pub trait Visitor {
     type Result;
 
+    fn visit_assign_expr(&self, expr: &Assign) -> Self::Result;
     fn visit_binary_expr(&self, expr: &Binary) -> Self::Result;
+    fn visit_call_expr(&self, expr: &Call) -> Self::Result;
+    fn visit_comma_expr(&self, expr: &Comma) -> Self::Result;
+    fn visit_conditional_expr(&self, expr: &Conditional) -> Self::Result;
     fn visit_expr(&self, name: &Expr) -> Self::Result;
+    fn visit_get_expr(&self, expr: &Get) -> Self::Result;
     fn visit_grouping_expr(&self, name: &Grouping) -> Self::Result;
+    fn visit_index_expr(&self, expr: &Index) -> Self::Result;
+    fn visit_interpolation_expr(&self, expr: &Interpolation) -> Self::Result;
+    fn visit_index_set_expr(&self, expr: &IndexSet) -> Self::Result;
+    fn visit_lambda_expr(&self, expr: &Rc<Lambda>) -> Self::Result;
+    fn visit_list_expr(&self, expr: &List) -> Self::Result;
     fn visit_literal_expr(&self, expr: &Literal) -> Self::Result;
+    fn visit_logical_expr(&self, expr: &Logical) -> Self::Result;
+    fn visit_map_expr(&self, expr: &Map) -> Self::Result;
+    fn visit_set_expr(&self, expr: &Set) -> Self::Result;
+    fn visit_super_expr(&self, expr: &Super) -> Self::Result;
+    fn visit_this_expr(&self, expr: &This) -> Self::Result;
     fn visit_unary_expr(&self, expr: &Unary) -> Self::Result;
+    fn visit_variable_expr(&self, expr: &Variable) -> Self::Result;
 }
 
 #[derive(Debug)]
 pub enum Expr {
+    Assign(Box<Assign>),
     Binary(Box<Binary>),
+    Call(Box<Call>),
+    Comma(Box<Comma>),
+    Conditional(Box<Conditional>),
+    Get(Box<Get>),
+    Index(Box<Index>),
+    IndexSet(Box<IndexSet>),
+    Interpolation(Interpolation),
+    Lambda(Rc<Lambda>),
+    List(List),
     Literal(Literal),
+    Logical(Box<Logical>),
+    Map(Map),
+    Set(Box<Set>),
+    Super(Super),
+    This(This),
     Unary(Box<Unary>),
     Grouping(Box<Grouping>),
+    Variable(Variable),
 }
 
 impl Acceptor for Expr {
@@ -28,9 +63,67 @@ impl Acceptor for Expr {
     }
 }
 
+impl Expr {
+    // The stretch of source the whole expression was parsed from, operands included.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Assign(assign) => assign.name.span.to(assign.value.span()),
+            Expr::Binary(binary) => binary.left.span().to(binary.right.span()),
+            Expr::Call(call) => call.callee.span().to(call.paren.span),
+            Expr::Comma(comma) => comma.left.span().to(comma.right.span()),
+            Expr::Conditional(conditional) => conditional
+                .condition
+                .span()
+                .to(conditional.else_branch.span()),
+            Expr::Get(get) => get.object.span().to(get.name.span),
+            Expr::Grouping(grouping) => grouping.span,
+            Expr::Index(index) => index.object.span().to(index.bracket.span),
+            Expr::IndexSet(index_set) => index_set.object.span().to(index_set.value.span()),
+            Expr::Interpolation(interpolation) => interpolation.span,
+            Expr::Lambda(lambda) => lambda.span,
+            Expr::List(list) => list.span,
+            Expr::Literal(literal) => literal.span,
+            Expr::Logical(logical) => logical.left.span().to(logical.right.span()),
+            Expr::Map(map) => map.span,
+            Expr::Set(set) => set.object.span().to(set.value.span()),
+            Expr::Super(super_expr) => super_expr.keyword.span.to(super_expr.method.span),
+            Expr::This(this) => this.keyword.span,
+            Expr::Unary(unary) => unary.operator.span.to(unary.right.span()),
+            Expr::Variable(variable) => variable.name.span,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Assign {
+    pub name: Token,
+    pub value: Expr,
+    pub depth: Cell<Option<usize>>,
+}
+
+impl Acceptor for Assign {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_assign_expr(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct Get {
+    pub object: Expr,
+    pub name: Token,
+}
+
+impl Acceptor for Get {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_get_expr(self)
+    }
+}
+
 #[derive(Debug)]
 pub struct Grouping {
     pub expression: Expr,
+    // covers the parentheses too, which no token inside the group does
+    pub span: Span,
 }
 
 impl Acceptor for Grouping {
@@ -53,7 +146,124 @@ impl Acceptor for Binary {
 }
 
 #[derive(Debug)]
-pub enum Literal {
+pub struct Call {
+    pub callee: Expr,
+    pub paren: Token,
+    pub arguments: Vec<Expr>,
+}
+
+impl Acceptor for Call {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_call_expr(self)
+    }
+}
+
+// `left, right` evaluates both and keeps the right one.
+#[derive(Debug)]
+pub struct Comma {
+    pub left: Expr,
+    pub right: Expr,
+}
+
+impl Acceptor for Comma {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_comma_expr(self)
+    }
+}
+
+// `condition ? then_branch : else_branch`
+#[derive(Debug)]
+pub struct Conditional {
+    pub condition: Expr,
+    pub then_branch: Expr,
+    pub else_branch: Expr,
+}
+
+impl Acceptor for Conditional {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_conditional_expr(self)
+    }
+}
+
+// `object[index]`
+#[derive(Debug)]
+pub struct Index {
+    pub object: Expr,
+    // the closing bracket, used to report a bad index
+    pub bracket: Token,
+    pub index: Expr,
+}
+
+impl Acceptor for Index {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_index_expr(self)
+    }
+}
+
+// `object[index] = value`
+#[derive(Debug)]
+pub struct IndexSet {
+    pub object: Expr,
+    pub bracket: Token,
+    pub index: Expr,
+    pub value: Expr,
+}
+
+impl Acceptor for IndexSet {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_index_set_expr(self)
+    }
+}
+
+// `"a ${b} c"`, split into the string pieces and the embedded expressions in
+// source order. Every part is stringified, whatever type it turns out to be.
+#[derive(Debug)]
+pub struct Interpolation {
+    pub parts: Vec<Expr>,
+    pub span: Span,
+}
+
+impl Acceptor for Interpolation {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_interpolation_expr(self)
+    }
+}
+
+// `fun (params) { body }`, a function with no name.
+#[derive(Debug)]
+pub struct Lambda {
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+impl Acceptor for Rc<Lambda> {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_lambda_expr(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct List {
+    pub elements: Vec<Expr>,
+    // covers the brackets, an empty list has nothing else to point at
+    pub span: Span,
+}
+
+impl Acceptor for List {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_list_expr(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct Literal {
+    pub value: LiteralValue,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum LiteralValue {
     Number(f64),
     String(String),
     Boolean(bool),
@@ -66,6 +276,79 @@ impl Acceptor for Literal {
     }
 }
 
+#[derive(Debug)]
+pub struct Logical {
+    pub left: Expr,
+    pub operator: Token,
+    pub right: Expr,
+}
+
+impl Acceptor for Logical {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_logical_expr(self)
+    }
+}
+
+// `{key: value, ...}`, entries in source order.
+#[derive(Debug)]
+pub struct Map {
+    pub entries: Vec<Entry>,
+    // covers the braces, an empty map has nothing else to point at
+    pub span: Span,
+}
+
+impl Acceptor for Map {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_map_expr(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct Entry {
+    pub key: Expr,
+    // the `:` after the key, used to report a key that can't be hashed
+    pub colon: Token,
+    pub value: Expr,
+}
+
+#[derive(Debug)]
+pub struct Set {
+    pub object: Expr,
+    pub name: Token,
+    pub value: Expr,
+}
+
+impl Acceptor for Set {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_set_expr(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct Super {
+    pub keyword: Token,
+    pub method: Token,
+    pub depth: Cell<Option<usize>>,
+}
+
+impl Acceptor for Super {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_super_expr(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct This {
+    pub keyword: Token,
+    pub depth: Cell<Option<usize>>,
+}
+
+impl Acceptor for This {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_this_expr(self)
+    }
+}
+
 #[derive(Debug)]
 pub struct Unary {
     pub operator: Token,
@@ -77,3 +360,17 @@ impl Acceptor for Unary {
         visitor.visit_unary_expr(self)
     }
 }
+
+#[derive(Debug)]
+// `depth` is how many scopes up the referenced variable lives. The resolver fills
+// it in before execution, and it stays None for globals.
+pub struct Variable {
+    pub name: Token,
+    pub depth: Cell<Option<usize>>,
+}
+
+impl Acceptor for Variable {
+    fn accept<V: Visitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_variable_expr(self)
+    }
+}
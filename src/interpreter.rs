@@ -0,0 +1,678 @@
+use crate::callable::{Callable, LoxFunction};
+use crate::class::LoxClass;
+use crate::environment::Environment;
+use crate::expr::*;
+use crate::map::LoxMap;
+use crate::natives;
+use crate::stmt::*;
+use crate::token::*;
+use crate::value::Value;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const MAX_CALL_DEPTH: usize = 1000;
+
+// Only the span of the offending token is kept, so errors stay cheap to pass
+// around in every `Result`.
+pub struct RuntimeError {
+    pub span: Span,
+    pub message: String,
+    pub help: Option<&'static str>,
+}
+
+impl RuntimeError {
+    pub fn new(token: &Token, message: String) -> Self {
+        Self {
+            span: token.span,
+            message,
+            help: None,
+        }
+    }
+
+    pub fn with_help(mut self, help: &'static str) -> Self {
+        self.help = Some(help);
+        self
+    }
+}
+
+// Anything that cuts statement execution short. A `return` travels up the
+// same path as an error until the function call that catches it.
+pub enum Unwind {
+    Error(RuntimeError),
+    Return(Value),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Self {
+        Unwind::Error(error)
+    }
+}
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    // the innermost scope, swapped out whenever a block is entered or left
+    environment: RefCell<Rc<RefCell<Environment>>>,
+    // calls in progress, capped so runaway recursion fails before the host stack does
+    call_depth: Cell<usize>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut globals = Environment::new();
+
+        natives::define(&mut globals);
+
+        let globals = Rc::new(RefCell::new(globals));
+
+        Self {
+            environment: RefCell::new(Rc::clone(&globals)),
+            globals,
+            call_depth: Cell::new(0),
+        }
+    }
+    pub fn interpret(&self, statements: &[Stmt]) -> Result<(), RuntimeError> {
+        for statement in statements {
+            match statement.accept(self) {
+                Ok(()) => {}
+                Err(Unwind::Error(error)) => return Err(error),
+                // a top-level return simply ends the script
+                Err(Unwind::Return(_)) => return Ok(()),
+            }
+        }
+
+        Ok(())
+    }
+    pub fn execute_block(
+        &self,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Result<(), Unwind> {
+        let previous = self.environment.replace(Rc::new(RefCell::new(environment)));
+
+        let result = statements
+            .iter()
+            .try_for_each(|statement| statement.accept(self));
+
+        // restore the outer scope even when the block bailed out with an error
+        self.environment.replace(previous);
+
+        result
+    }
+    fn current_environment(&self) -> Rc<RefCell<Environment>> {
+        Rc::clone(&self.environment.borrow())
+    }
+    fn look_up_variable(&self, name: &Token, depth: Option<usize>) -> Result<Value, RuntimeError> {
+        match depth {
+            Some(distance) => self.current_environment().borrow().get_at(distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+}
+
+impl StmtVisitor for Interpreter {
+    type Result = Result<(), Unwind>;
+
+    fn visit_stmt(&self, stmt: &Stmt) -> Self::Result {
+        match stmt {
+            Stmt::Block(block) => self.visit_block_stmt(block),
+            Stmt::Class(class) => self.visit_class_stmt(class),
+            Stmt::Expression(expression) => self.visit_expression_stmt(expression),
+            Stmt::Function(function) => self.visit_function_stmt(function),
+            Stmt::If(if_stmt) => self.visit_if_stmt(if_stmt),
+            Stmt::Print(print) => self.visit_print_stmt(print),
+            Stmt::Return(return_stmt) => self.visit_return_stmt(return_stmt),
+            Stmt::Var(var) => self.visit_var_stmt(var),
+            Stmt::While(while_stmt) => self.visit_while_stmt(while_stmt),
+        }
+    }
+
+    fn visit_block_stmt(&self, stmt: &Block) -> Self::Result {
+        let environment = Environment::new_enclosed(self.current_environment());
+
+        self.execute_block(&stmt.statements, environment)
+    }
+
+    fn visit_class_stmt(&self, stmt: &Class) -> Self::Result {
+        let superclass = match &stmt.superclass {
+            Some(superclass) => match self.visit_variable_expr(superclass)? {
+                Value::Class(class) => Some(class),
+                _ => {
+                    return Err(Unwind::Error(RuntimeError::new(
+                        &superclass.name,
+                        "Superclass must be a class.".to_owned(),
+                    )))
+                }
+            },
+            None => None,
+        };
+
+        let name = stmt.name.lexeme.clone();
+
+        // defined up front so methods can refer to their own class
+        self.current_environment()
+            .borrow_mut()
+            .define(name.clone(), Value::Nil);
+
+        let enclosing = self.current_environment();
+
+        // methods of a subclass close over an extra scope holding `super`
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::new_enclosed(Rc::clone(&enclosing));
+
+            environment.define("super".to_owned(), Value::Class(Rc::clone(superclass)));
+            self.environment.replace(Rc::new(RefCell::new(environment)));
+        }
+
+        let mut methods: HashMap<String, Rc<LoxFunction>> = HashMap::new();
+
+        for method in &stmt.methods {
+            let function = LoxFunction::new(
+                Rc::clone(method),
+                self.current_environment(),
+                method.name.lexeme == "init",
+            );
+
+            methods.insert(method.name.lexeme.clone(), Rc::new(function));
+        }
+
+        let class = LoxClass {
+            name,
+            superclass,
+            methods,
+        };
+
+        self.environment.replace(Rc::clone(&enclosing));
+
+        enclosing
+            .borrow_mut()
+            .assign(&stmt.name, Value::Class(Rc::new(class)))?;
+
+        Ok(())
+    }
+
+    fn visit_expression_stmt(&self, stmt: &Expression) -> Self::Result {
+        stmt.expression.accept(self)?;
+
+        Ok(())
+    }
+
+    fn visit_function_stmt(&self, stmt: &Rc<Function>) -> Self::Result {
+        // capture the scope the function is declared in, not the one it is called from
+        let function = LoxFunction::new(Rc::clone(stmt), self.current_environment(), false);
+
+        self.current_environment()
+            .borrow_mut()
+            .define(stmt.name.lexeme.clone(), Value::Function(Rc::new(function)));
+
+        Ok(())
+    }
+
+    fn visit_if_stmt(&self, stmt: &If) -> Self::Result {
+        if stmt.condition.accept(self)?.is_truthy() {
+            stmt.then_branch.accept(self)?;
+        } else if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self)?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_print_stmt(&self, stmt: &Print) -> Self::Result {
+        let value = stmt.expression.accept(self)?;
+        println!("{value}");
+
+        Ok(())
+    }
+
+    fn visit_return_stmt(&self, stmt: &Return) -> Self::Result {
+        let value = match &stmt.value {
+            Some(value) => value.accept(self)?,
+            None => Value::Nil,
+        };
+
+        Err(Unwind::Return(value))
+    }
+
+    fn visit_var_stmt(&self, stmt: &Var) -> Self::Result {
+        let value = match &stmt.initializer {
+            Some(initializer) => initializer.accept(self)?,
+            None => Value::Nil,
+        };
+        let name = stmt.name.lexeme.clone();
+
+        self.current_environment().borrow_mut().define(name, value);
+
+        Ok(())
+    }
+
+    fn visit_while_stmt(&self, stmt: &While) -> Self::Result {
+        while stmt.condition.accept(self)?.is_truthy() {
+            stmt.body.accept(self)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Visitor for Interpreter {
+    type Result = Result<Value, RuntimeError>;
+
+    fn visit_expr(&self, expr: &Expr) -> Self::Result {
+        match expr {
+            Expr::Assign(assign) => self.visit_assign_expr(assign),
+            Expr::Binary(binary) => self.visit_binary_expr(binary),
+            Expr::Call(call) => self.visit_call_expr(call),
+            Expr::Comma(comma) => self.visit_comma_expr(comma),
+            Expr::Conditional(conditional) => self.visit_conditional_expr(conditional),
+            Expr::Get(get) => self.visit_get_expr(get),
+            Expr::Grouping(grouping) => self.visit_grouping_expr(grouping),
+            Expr::Index(index) => self.visit_index_expr(index),
+            Expr::IndexSet(index_set) => self.visit_index_set_expr(index_set),
+            Expr::Interpolation(interpolation) => self.visit_interpolation_expr(interpolation),
+            Expr::Lambda(lambda) => self.visit_lambda_expr(lambda),
+            Expr::List(list) => self.visit_list_expr(list),
+            Expr::Literal(literal) => self.visit_literal_expr(literal),
+            Expr::Logical(logical) => self.visit_logical_expr(logical),
+            Expr::Map(map) => self.visit_map_expr(map),
+            Expr::Set(set) => self.visit_set_expr(set),
+            Expr::Super(super_expr) => self.visit_super_expr(super_expr),
+            Expr::This(this) => self.visit_this_expr(this),
+            Expr::Unary(unary) => self.visit_unary_expr(unary),
+            Expr::Variable(variable) => self.visit_variable_expr(variable),
+        }
+    }
+
+    fn visit_assign_expr(&self, expr: &Assign) -> Self::Result {
+        let value = expr.value.accept(self)?;
+
+        match expr.depth.get() {
+            Some(distance) => self.current_environment().borrow_mut().assign_at(
+                distance,
+                &expr.name,
+                value.clone(),
+            )?,
+            None => self
+                .globals
+                .borrow_mut()
+                .assign(&expr.name, value.clone())?,
+        };
+
+        Ok(value)
+    }
+
+    fn visit_binary_expr(&self, expr: &Binary) -> Self::Result {
+        let left = expr.left.accept(self)?;
+        let right = expr.right.accept(self)?;
+        let value = match (expr.operator.kind, left, right) {
+            (TokenKind::Plus, Value::Number(l), Value::Number(r)) => Value::Number(l + r),
+            (TokenKind::Plus, Value::String(l), Value::String(r)) => Value::String(l + &r),
+            (TokenKind::Plus, _, _) => {
+                return Err(RuntimeError::new(
+                    &expr.operator,
+                    "Operands must be two numbers or two strings.".to_owned(),
+                ))
+            }
+            (TokenKind::Minus, Value::Number(l), Value::Number(r)) => Value::Number(l - r),
+            (TokenKind::Star, Value::Number(l), Value::Number(r)) => Value::Number(l * r),
+            (TokenKind::Slash, Value::Number(l), Value::Number(r)) => Value::Number(l / r),
+            (TokenKind::Percent, Value::Number(l), Value::Number(r)) => Value::Number(l % r),
+            (TokenKind::StarStar, Value::Number(l), Value::Number(r)) => Value::Number(l.powf(r)),
+            (
+                TokenKind::Ampersand
+                | TokenKind::Pipe
+                | TokenKind::Caret
+                | TokenKind::LessLess
+                | TokenKind::GreaterGreater,
+                Value::Number(l),
+                Value::Number(r),
+            ) => bitwise(&expr.operator, l, r)?,
+            (TokenKind::Greater, Value::Number(l), Value::Number(r)) => Value::Boolean(l > r),
+            (TokenKind::GreaterEqual, Value::Number(l), Value::Number(r)) => Value::Boolean(l >= r),
+            (TokenKind::Less, Value::Number(l), Value::Number(r)) => Value::Boolean(l < r),
+            (TokenKind::LessEqual, Value::Number(l), Value::Number(r)) => Value::Boolean(l <= r),
+            (TokenKind::EqualEqual, l, r) => Value::Boolean(l == r),
+            (TokenKind::BangEqual, l, r) => Value::Boolean(l != r),
+            _ => {
+                return Err(RuntimeError::new(
+                    &expr.operator,
+                    "Operands must be numbers.".to_owned(),
+                ))
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn visit_call_expr(&self, expr: &Call) -> Self::Result {
+        let callee = expr.callee.accept(self)?;
+
+        let arguments = expr
+            .arguments
+            .iter()
+            .map(|argument| argument.accept(self))
+            .collect::<Result<Vec<Value>, RuntimeError>>()?;
+
+        let callable: &dyn Callable = match &callee {
+            Value::Function(function) => function.as_ref(),
+            Value::NativeFunction(function) => function.as_ref(),
+            Value::Class(class) => class,
+            _ => {
+                return Err(RuntimeError::new(
+                    &expr.paren,
+                    "Can only call functions and classes.".to_owned(),
+                ))
+            }
+        };
+
+        if arguments.len() != callable.arity() {
+            return Err(RuntimeError::new(
+                &expr.paren,
+                format!(
+                    "Expected {} arguments but got {}.",
+                    callable.arity(),
+                    arguments.len()
+                ),
+            ));
+        }
+
+        let depth = self.call_depth.get();
+
+        if depth >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::new(&expr.paren, "Stack overflow.".to_owned())
+                .with_help("check that the recursion reaches a case that stops it"));
+        }
+
+        self.call_depth.set(depth + 1);
+
+        let result = callable.call(self, &expr.paren, arguments);
+
+        // restored on errors too, the REPL keeps using this interpreter afterwards
+        self.call_depth.set(depth);
+
+        result
+    }
+
+    fn visit_comma_expr(&self, expr: &Comma) -> Self::Result {
+        expr.left.accept(self)?;
+        expr.right.accept(self)
+    }
+
+    fn visit_conditional_expr(&self, expr: &Conditional) -> Self::Result {
+        if expr.condition.accept(self)?.is_truthy() {
+            expr.then_branch.accept(self)
+        } else {
+            expr.else_branch.accept(self)
+        }
+    }
+
+    fn visit_get_expr(&self, expr: &Get) -> Self::Result {
+        let object = expr.object.accept(self)?;
+
+        match &object {
+            Value::Instance(instance) => instance.borrow().get(&expr.name, &object),
+            _ => Err(RuntimeError::new(
+                &expr.name,
+                "Only instances have properties.".to_owned(),
+            )),
+        }
+    }
+
+    fn visit_grouping_expr(&self, expr: &Grouping) -> Self::Result {
+        expr.expression.accept(self)
+    }
+
+    fn visit_index_expr(&self, expr: &Index) -> Self::Result {
+        let object = expr.object.accept(self)?;
+        let index = expr.index.accept(self)?;
+
+        match object {
+            Value::List(list) => {
+                let list = list.borrow();
+                let position = list_index(&expr.bracket, &index, list.len())?;
+
+                Ok(list[position].clone())
+            }
+            Value::Map(map) => map.borrow().get(&expr.bracket, &index),
+            _ => Err(RuntimeError::new(
+                &expr.bracket,
+                "Only lists and maps can be indexed.".to_owned(),
+            )),
+        }
+    }
+
+    fn visit_index_set_expr(&self, expr: &IndexSet) -> Self::Result {
+        let object = expr.object.accept(self)?;
+        let index = expr.index.accept(self)?;
+
+        if !matches!(object, Value::List(_) | Value::Map(_)) {
+            return Err(RuntimeError::new(
+                &expr.bracket,
+                "Only lists and maps can be indexed.".to_owned(),
+            ));
+        }
+
+        // evaluated before borrowing, the value may well read from the same collection
+        let value = expr.value.accept(self)?;
+
+        match object {
+            Value::List(list) => {
+                let mut list = list.borrow_mut();
+                let position = list_index(&expr.bracket, &index, list.len())?;
+
+                list[position] = value.clone();
+            }
+            Value::Map(map) => map.borrow_mut().set(&expr.bracket, index, value.clone())?,
+            _ => unreachable!("checked above"),
+        }
+
+        Ok(value)
+    }
+
+    fn visit_interpolation_expr(&self, expr: &Interpolation) -> Self::Result {
+        let mut string = String::new();
+
+        for part in &expr.parts {
+            string.push_str(&part.accept(self)?.to_string());
+        }
+
+        Ok(Value::String(string))
+    }
+
+    fn visit_lambda_expr(&self, expr: &Rc<Lambda>) -> Self::Result {
+        // closes over the scope the expression is evaluated in, same as a declaration
+        let function = LoxFunction::lambda(Rc::clone(expr), self.current_environment());
+
+        Ok(Value::Function(Rc::new(function)))
+    }
+
+    fn visit_list_expr(&self, expr: &List) -> Self::Result {
+        let elements = expr
+            .elements
+            .iter()
+            .map(|element| element.accept(self))
+            .collect::<Result<Vec<Value>, RuntimeError>>()?;
+
+        Ok(Value::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn visit_literal_expr(&self, expr: &Literal) -> Self::Result {
+        let value = match &expr.value {
+            LiteralValue::Number(number) => Value::Number(*number),
+            LiteralValue::String(string) => Value::String(string.clone()),
+            LiteralValue::Boolean(boolean) => Value::Boolean(*boolean),
+            LiteralValue::Nil => Value::Nil,
+        };
+
+        Ok(value)
+    }
+
+    // Returns the operand that decided the outcome rather than a bare boolean.
+    fn visit_logical_expr(&self, expr: &Logical) -> Self::Result {
+        let left = expr.left.accept(self)?;
+
+        let short_circuits = match expr.operator.kind {
+            TokenKind::Or => left.is_truthy(),
+            _ => !left.is_truthy(),
+        };
+
+        if short_circuits {
+            return Ok(left);
+        }
+
+        expr.right.accept(self)
+    }
+
+    fn visit_map_expr(&self, expr: &Map) -> Self::Result {
+        let mut map = LoxMap::new();
+
+        // a repeated key keeps the last value, like assigning to it twice
+        for entry in &expr.entries {
+            let key = entry.key.accept(self)?;
+            let value = entry.value.accept(self)?;
+
+            map.set(&entry.colon, key, value)?;
+        }
+
+        Ok(Value::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn visit_set_expr(&self, expr: &Set) -> Self::Result {
+        let object = expr.object.accept(self)?;
+
+        let Value::Instance(instance) = object else {
+            return Err(RuntimeError::new(
+                &expr.name,
+                "Only instances have fields.".to_owned(),
+            ));
+        };
+
+        let value = expr.value.accept(self)?;
+
+        instance.borrow_mut().set(&expr.name, value.clone());
+
+        Ok(value)
+    }
+
+    fn visit_super_expr(&self, expr: &Super) -> Self::Result {
+        let environment = self.current_environment();
+        let this = Token::identifier("this", expr.keyword.span);
+
+        // the resolver always binds `super`, and `this` sits in the scope right inside it
+        let distance = expr.depth.get().unwrap_or_default();
+        let superclass = environment.borrow().get_at(distance, &expr.keyword)?;
+        let object = environment
+            .borrow()
+            .get_at(distance.saturating_sub(1), &this)?;
+        let method = match &superclass {
+            Value::Class(class) => class.find_method(&expr.method.lexeme),
+            _ => None,
+        };
+
+        match method {
+            Some(method) => Ok(Value::Function(Rc::new(method.bind(object)))),
+            None => Err(RuntimeError::new(
+                &expr.method,
+                format!("Undefined property '{}'.", expr.method.lexeme),
+            )),
+        }
+    }
+
+    fn visit_this_expr(&self, expr: &This) -> Self::Result {
+        self.look_up_variable(&expr.keyword, expr.depth.get())
+    }
+
+    fn visit_unary_expr(&self, expr: &Unary) -> Self::Result {
+        let right = expr.right.accept(self)?;
+        match (expr.operator.kind, right) {
+            (TokenKind::Minus, Value::Number(number)) => Ok(Value::Number(-number)),
+            (TokenKind::Bang, value) => Ok(Value::Boolean(!value.is_truthy())),
+            (TokenKind::Tilde, Value::Number(number)) => {
+                Ok(Value::Number(!integer(&expr.operator, number)? as f64))
+            }
+            _ => Err(RuntimeError::new(
+                &expr.operator,
+                "Operand must be a number.".to_owned(),
+            )),
+        }
+    }
+
+    fn visit_variable_expr(&self, expr: &Variable) -> Self::Result {
+        self.look_up_variable(&expr.name, expr.depth.get())
+    }
+}
+
+// Past 2^53 a double can't tell neighbouring integers apart, so there's no
+// exact integer for the bitwise operators to work on.
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+fn integer(operator: &Token, number: f64) -> Result<i64, RuntimeError> {
+    if number.fract() == 0.0 && number.abs() <= MAX_SAFE_INTEGER {
+        return Ok(number as i64);
+    }
+
+    Err(RuntimeError::new(
+        operator,
+        format!("Operands of '{operator}' must be integers, got {number}."),
+    ))
+}
+
+fn bitwise(operator: &Token, left: f64, right: f64) -> Result<Value, RuntimeError> {
+    let left = integer(operator, left)?;
+    let right = integer(operator, right)?;
+
+    let result = match operator.kind {
+        TokenKind::Ampersand => left & right,
+        TokenKind::Pipe => left | right,
+        TokenKind::Caret => left ^ right,
+        TokenKind::LessLess | TokenKind::GreaterGreater => {
+            if !(0..64).contains(&right) {
+                return Err(RuntimeError::new(
+                    operator,
+                    format!("Shift amount must be between 0 and 63, got {right}."),
+                ));
+            }
+
+            match operator.kind {
+                TokenKind::LessLess => left << right,
+                _ => left >> right,
+            }
+        }
+        _ => unreachable!("not a bitwise operator"),
+    };
+
+    Ok(Value::Number(result as f64))
+}
+
+// Turns a Lox index into a position in a list of `length` elements. Negative
+// indices count back from the end, so `-1` is the last element.
+fn list_index(bracket: &Token, index: &Value, length: usize) -> Result<usize, RuntimeError> {
+    let Value::Number(number) = *index else {
+        return Err(RuntimeError::new(
+            bracket,
+            "List index must be a number.".to_owned(),
+        ));
+    };
+
+    if number.fract() != 0.0 {
+        return Err(RuntimeError::new(
+            bracket,
+            format!("List index must be an integer, got {number}."),
+        ));
+    }
+
+    let position = if number < 0.0 {
+        number + length as f64
+    } else {
+        number
+    };
+
+    if position < 0.0 || position >= length as f64 {
+        return Err(RuntimeError::new(
+            bracket,
+            format!("Index {number} is out of range for a list of length {length}."),
+        ));
+    }
+
+    Ok(position as usize)
+}
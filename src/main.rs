@@ -1,17 +1,57 @@
 use crate::ast::Ast;
+use crate::diagnostics::Reporter;
+use crate::interpreter::Interpreter;
 use crate::parser::Parser;
+use crate::resolver::Resolver;
 use crate::scanner::Scanner;
+use crate::stmt::Stmt;
 use std::io::Write;
-use std::{env, fs, io};
+use std::{env, fs, io, process, thread};
 
 //* modules must be declared here so they can use each other
 mod ast;
+mod callable;
+mod class;
+mod diagnostics;
+mod environment;
 mod expr;
+mod interpreter;
+mod map;
+mod natives;
 mod parser;
+mod resolver;
 mod scanner;
+mod stmt;
 mod token;
+mod value;
+
+// exit codes follow the BSD sysexits.h conventions
+const EX_USAGE: i32 = 64;
+const EX_DATAERR: i32 = 65;
+const EX_NOINPUT: i32 = 66;
+const EX_SOFTWARE: i32 = 70;
+
+// Every Lox call goes through a good few Rust frames in a tree-walker, more so
+// in debug builds, so the default main thread stack runs out well before the
+// interpreter's own call depth limit does.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
 
 fn main() {
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run_main)
+        .unwrap_or_else(|error| {
+            eprintln!("Could not start the interpreter: {error}");
+            process::exit(EX_SOFTWARE);
+        });
+
+    // a panic has already been printed by the hook, only the exit code is left
+    if interpreter.join().is_err() {
+        process::exit(EX_SOFTWARE);
+    }
+}
+
+fn run_main() {
     let args: Vec<String> = env::args().collect();
 
     match args.len() {
@@ -22,39 +62,115 @@ fn main() {
             let path = &args[1];
             run_file(path);
         }
-        _ => panic!("Please either provide a file path or no arguments"),
+        3 if args[1] == "--ast" => {
+            let path = &args[2];
+            print_ast(path);
+        }
+        _ => {
+            eprintln!("Usage: rlox [--ast] [script]");
+            process::exit(EX_USAGE);
+        }
     };
 }
 
-fn run(source: String) {
-    let mut scanner = Scanner::from(source.clone());
-    let tokens = scanner.scan_tokens();
+fn parse(source: &str, reporter: &Reporter) -> Option<Vec<Stmt>> {
+    let mut scanner = Scanner::from(source.to_owned());
+    let (tokens, errors) = scanner.scan_tokens();
+
+    if !errors.is_empty() {
+        for error in errors {
+            reporter.report(error);
+        }
+
+        return None;
+    }
+
+    let mut parser = Parser::new(tokens);
+    let (statements, errors) = parser.parse();
+
+    if !errors.is_empty() {
+        for error in errors {
+            reporter.report(error);
+        }
+
+        return None;
+    }
+
+    let resolver = Resolver::new();
+    let errors = resolver.resolve(&statements);
+
+    if !errors.is_empty() {
+        for error in errors {
+            reporter.report(error);
+        }
+
+        return None;
+    }
+
+    Some(statements)
+}
+
+// Returns the exit code to use when the source failed to compile or run.
+fn run(file_name: &str, source: &str, interpreter: &Interpreter) -> Result<(), i32> {
+    let reporter = Reporter::new(file_name, source);
+    let statements = parse(source, &reporter).ok_or(EX_DATAERR)?;
 
-    let mut parser = Parser::new(tokens, source.clone());
-    let expression = parser.parse();
+    interpreter.interpret(&statements).map_err(|error| {
+        reporter.report(error);
+        EX_SOFTWARE
+    })
+}
 
-    let ast = Ast::new(expression);
-    ast.print();
+fn read_file(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("Could not read \"{path}\": {error}");
+        process::exit(EX_NOINPUT);
+    })
 }
 
 fn run_file(path: &str) {
-    let source = fs::read_to_string(path).expect("No such file.");
-    run(source);
+    let source = read_file(path);
+    let interpreter = Interpreter::new();
+
+    if let Err(code) = run(path, &source, &interpreter) {
+        process::exit(code);
+    }
+}
+
+fn print_ast(path: &str) {
+    let source = read_file(path);
+
+    match parse(&source, &Reporter::new(path, &source)) {
+        Some(statements) => {
+            let ast = Ast::new(statements);
+            ast.print();
+        }
+        None => process::exit(EX_DATAERR),
+    }
 }
 
 fn run_prompt() {
     println!("Start typing your commands...");
 
+    // one interpreter for the whole session so variables survive between lines
+    let interpreter = Interpreter::new();
+
     loop {
         print!("> ");
         std::io::stdout().flush().unwrap();
 
         let mut source = String::new();
 
-        io::stdin()
+        let read = io::stdin()
             .read_line(&mut source)
             .expect("Could not read input.");
 
-        run(source);
+        // Ctrl-D
+        if read == 0 {
+            break;
+        }
+
+        // errors were already reported, and a typo shouldn't end the session
+        let _ = run("<stdin>", &source, &interpreter);
     }
 }
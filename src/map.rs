@@ -0,0 +1,113 @@
+use crate::interpreter::RuntimeError;
+use crate::token::Token;
+use crate::value::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+// The hashable subset of values. Numbers hash by their bits, with `-0` folded
+// into `0` since the two compare equal.
+#[derive(PartialEq, Eq, Hash)]
+enum Key {
+    Number(u64),
+    String(String),
+    Boolean(bool),
+    Nil,
+}
+
+impl Key {
+    fn new(token: &Token, value: &Value) -> Result<Key, RuntimeError> {
+        match value {
+            Value::Number(number) if *number == 0.0 => Ok(Key::Number(0.0f64.to_bits())),
+            Value::Number(number) => Ok(Key::Number(number.to_bits())),
+            Value::String(string) => Ok(Key::String(string.clone())),
+            Value::Boolean(boolean) => Ok(Key::Boolean(*boolean)),
+            Value::Nil => Ok(Key::Nil),
+            _ => Err(RuntimeError::new(
+                token,
+                "Map keys must be strings, numbers, booleans or nil.".to_owned(),
+            )),
+        }
+    }
+}
+
+// Entries are kept in insertion order so iterating over a map, and printing
+// it, gives the same result on every run. `indices` points into `entries`.
+pub struct LoxMap {
+    entries: Vec<(Value, Value)>,
+    indices: HashMap<Key, usize>,
+}
+
+impl LoxMap {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            indices: HashMap::new(),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn get(&self, token: &Token, key: &Value) -> Result<Value, RuntimeError> {
+        match self.indices.get(&Key::new(token, key)?) {
+            Some(&index) => Ok(self.entries[index].1.clone()),
+            None => Err(RuntimeError::new(
+                token,
+                format!("Key {} not found in map.", key.quoted()),
+            )
+            .with_help("use 'has' to check whether a map contains a key")),
+        }
+    }
+    // Overwriting a key keeps its original place in the order.
+    pub fn set(&mut self, token: &Token, key: Value, value: Value) -> Result<(), RuntimeError> {
+        let hashed = Key::new(token, &key)?;
+
+        match self.indices.get(&hashed) {
+            Some(&index) => self.entries[index].1 = value,
+            None => {
+                self.indices.insert(hashed, self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+
+        Ok(())
+    }
+    pub fn has(&self, token: &Token, key: &Value) -> Result<bool, RuntimeError> {
+        Ok(self.indices.contains_key(&Key::new(token, key)?))
+    }
+    pub fn remove(&mut self, token: &Token, key: &Value) -> Result<Option<Value>, RuntimeError> {
+        let Some(index) = self.indices.remove(&Key::new(token, key)?) else {
+            return Ok(None);
+        };
+
+        // everything after the removed entry shifts down by one
+        for later in self.indices.values_mut() {
+            if *later > index {
+                *later -= 1;
+            }
+        }
+
+        Ok(Some(self.entries.remove(index).1))
+    }
+    pub fn keys(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+}
+
+impl fmt::Display for LoxMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{")?;
+
+        for (i, (key, value)) in self.entries.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}: {}", key.quoted(), value.quoted())?;
+        }
+
+        write!(f, "}}")
+    }
+}
@@ -0,0 +1,171 @@
+use crate::callable::NativeFunction;
+use crate::environment::Environment;
+use crate::interpreter::RuntimeError;
+use crate::map::LoxMap;
+use crate::token::Token;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The functions every script starts out with in its global scope. The
+// interpreter checks the arity before calling one, so `arguments` always has
+// exactly `arity` values in it.
+pub fn define(globals: &mut Environment) {
+    let natives = [
+        NativeFunction {
+            name: "clock",
+            arity: 0,
+            function: clock,
+        },
+        NativeFunction {
+            name: "len",
+            arity: 1,
+            function: len,
+        },
+        NativeFunction {
+            name: "push",
+            arity: 2,
+            function: push,
+        },
+        NativeFunction {
+            name: "pop",
+            arity: 1,
+            function: pop,
+        },
+        NativeFunction {
+            name: "keys",
+            arity: 1,
+            function: keys,
+        },
+        NativeFunction {
+            name: "values",
+            arity: 1,
+            function: values,
+        },
+        NativeFunction {
+            name: "has",
+            arity: 2,
+            function: has,
+        },
+        NativeFunction {
+            name: "remove",
+            arity: 2,
+            function: remove,
+        },
+    ];
+
+    for native in natives {
+        globals.define(
+            native.name.to_owned(),
+            Value::NativeFunction(Rc::new(native)),
+        );
+    }
+}
+
+fn clock(_paren: &Token, _arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    Ok(Value::Number(now.as_secs_f64()))
+}
+
+// Strings are measured in characters rather than bytes.
+fn len(paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let length = match &arguments[0] {
+        Value::List(list) => list.borrow().len(),
+        Value::Map(map) => map.borrow().len(),
+        Value::String(string) => string.chars().count(),
+        _ => {
+            return Err(RuntimeError::new(
+                paren,
+                "Can only take the length of lists, maps and strings.".to_owned(),
+            ))
+        }
+    };
+
+    Ok(Value::Number(length as f64))
+}
+
+fn push(paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    list(paren, &arguments[0], "push")?
+        .borrow_mut()
+        .push(arguments[1].clone());
+
+    Ok(Value::Nil)
+}
+
+fn pop(paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    list(paren, &arguments[0], "pop")?
+        .borrow_mut()
+        .pop()
+        .ok_or_else(|| RuntimeError::new(paren, "Can't pop from an empty list.".to_owned()))
+}
+
+// Both come back as a new list in the map's insertion order, so a `for` loop
+// over them visits the entries in the order they were added.
+fn keys(paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let keys = map(paren, &arguments[0], "keys")?
+        .borrow()
+        .keys()
+        .cloned()
+        .collect();
+
+    Ok(Value::List(Rc::new(RefCell::new(keys))))
+}
+
+fn values(paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let values = map(paren, &arguments[0], "values")?
+        .borrow()
+        .values()
+        .cloned()
+        .collect();
+
+    Ok(Value::List(Rc::new(RefCell::new(values))))
+}
+
+fn has(paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let has = map(paren, &arguments[0], "has")?
+        .borrow()
+        .has(paren, &arguments[1])?;
+
+    Ok(Value::Boolean(has))
+}
+
+// Hands back the removed value, or nil if the key wasn't there.
+fn remove(paren: &Token, arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let removed = map(paren, &arguments[0], "remove")?
+        .borrow_mut()
+        .remove(paren, &arguments[1])?;
+
+    Ok(removed.unwrap_or(Value::Nil))
+}
+
+fn list<'a>(
+    paren: &Token,
+    value: &'a Value,
+    name: &str,
+) -> Result<&'a RefCell<Vec<Value>>, RuntimeError> {
+    match value {
+        Value::List(list) => Ok(list),
+        _ => Err(RuntimeError::new(
+            paren,
+            format!("Expected a list as the first argument to '{name}'."),
+        )),
+    }
+}
+
+fn map<'a>(
+    paren: &Token,
+    value: &'a Value,
+    name: &str,
+) -> Result<&'a RefCell<LoxMap>, RuntimeError> {
+    match value {
+        Value::Map(map) => Ok(map),
+        _ => Err(RuntimeError::new(
+            paren,
+            format!("Expected a map as the first argument to '{name}'."),
+        )),
+    }
+}
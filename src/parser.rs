@@ -1,260 +1,1137 @@
-use crate::{expr::*, token::*};
+use crate::{expr::*, stmt::*, token::*};
+use std::cell::Cell;
+use std::rc::Rc;
+
+const MAX_ARGUMENTS: usize = 255;
 
 pub struct ParseError {
     pub message: String,
-    pub line: usize,
+    pub span: Span,
+    pub help: Option<&'static str>,
+}
+
+// Binding powers from loosest to tightest. The derived ordering is what the
+// parser compares, so new levels only need to be slotted in at the right place.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Precedence {
+    None,
+    Comma,
+    Assignment,
+    Conditional,
+    Or,
+    And,
+    Equality,
+    Comparison,
+    BitwiseOr,
+    BitwiseXor,
+    BitwiseAnd,
+    Shift,
+    Term,
+    Factor,
+    Unary,
+    Power,
+    Call,
+    Primary,
+}
+
+impl Precedence {
+    fn next(self) -> Precedence {
+        match self {
+            Precedence::None => Precedence::Comma,
+            Precedence::Comma => Precedence::Assignment,
+            Precedence::Assignment => Precedence::Conditional,
+            Precedence::Conditional => Precedence::Or,
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::BitwiseOr,
+            Precedence::BitwiseOr => Precedence::BitwiseXor,
+            Precedence::BitwiseXor => Precedence::BitwiseAnd,
+            Precedence::BitwiseAnd => Precedence::Shift,
+            Precedence::Shift => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary => Precedence::Power,
+            Precedence::Power => Precedence::Call,
+            Precedence::Call | Precedence::Primary => Precedence::Primary,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Associativity {
+    Left,
+    Right,
+}
+
+type PrefixFn = fn(&mut Parser) -> Result<Expr, ParseError>;
+type InfixFn = fn(&mut Parser, Expr) -> Result<Expr, ParseError>;
+
+// How a token behaves in an expression: what it does when it starts one, what
+// it does when it follows one, and how tightly it binds in the latter case.
+struct Rule {
+    prefix: Option<PrefixFn>,
+    infix: Option<InfixFn>,
+    precedence: Precedence,
+    associativity: Associativity,
+}
+
+impl Rule {
+    // A left-associative operator parses its right operand one level up so it
+    // stops at the next operator of its own level, leaving `a - b - c` as
+    // `(a - b) - c`. A right-associative one lets the operand take it.
+    fn operand_precedence(&self) -> Precedence {
+        match self.associativity {
+            Associativity::Left => self.precedence.next(),
+            Associativity::Right => self.precedence,
+        }
+    }
 }
 
 pub struct Parser {
     tokens: Vec<Token>,
-    source: String,
     current: usize,
     errors: Vec<ParseError>,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>, source: String) -> Parser {
+    pub fn new(tokens: Vec<Token>) -> Parser {
         Parser {
             tokens,
-            source,
             current: 0,
             errors: Vec::new(),
         }
     }
 
-    pub fn parse(&mut self) -> Expr {
-        self.expression()
+    pub fn parse(&mut self) -> (Vec<Stmt>, Vec<ParseError>) {
+        let mut statements: Vec<Stmt> = Vec::new();
+
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize();
+                }
+            }
+        }
+
+        (statements, std::mem::take(&mut self.errors))
     }
 
-    fn expression(&mut self) -> Expr {
-        self.equality()
+    fn declaration(&mut self) -> Result<Stmt, ParseError> {
+        match self.peek().kind {
+            TokenKind::Var => {
+                self.advance();
+
+                self.var_declaration()
+            }
+            // `fun (` starts a lambda, left for the expression statement to parse
+            TokenKind::Fun if !self.check_next(TokenKind::LeftParen) => {
+                self.advance();
+
+                Ok(Stmt::Function(self.function("function")?))
+            }
+            TokenKind::Class => {
+                self.advance();
+
+                self.class_declaration()
+            }
+            _ => self.statement(),
+        }
     }
 
-    fn equality(&mut self) -> Expr {
-        let mut expr = self.comparison();
+    fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume(TokenKind::Identifier, "Expected class name.")?;
 
-        while self.is_not_at_end() {
-            match self.tokens[self.current] {
-                Token::BangEqual(lx, lt, ln) => {
-                    let operator = self.tokens[self.current].clone();
+        let mut superclass = None;
 
-                    self.advance();
+        if self.match_token(&[TokenKind::Less]) {
+            let name = self.consume(TokenKind::Identifier, "Expected superclass name.")?;
 
-                    expr = Expr::Binary(Box::new(Binary {
-                        left: expr,
-                        operator,
-                        right: self.comparison(),
-                    }))
-                }
-                Token::EqualEqual(lx, lt, ln) => {
-                    let operator = self.tokens[self.current].clone();
-
-                    self.advance();
-                    expr = Expr::Binary(Box::new(Binary {
-                        left: expr,
-                        operator,
-                        right: self.comparison(),
-                    }))
-                }
-                _ => break,
-            };
+            superclass = Some(Variable {
+                name,
+                depth: Cell::new(None),
+            });
         }
 
-        expr
+        self.consume(TokenKind::LeftBrace, "Expected '{' before class body.")?;
+
+        let mut methods: Vec<Rc<Function>> = Vec::new();
+
+        while !self.check(TokenKind::RightBrace) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+        }
+
+        self.consume(TokenKind::RightBrace, "Expected '}' after class body.")?;
+
+        Ok(Stmt::Class(Class {
+            name,
+            superclass,
+            methods,
+        }))
     }
 
-    fn comparison(&mut self) -> Expr {
-        let mut expr = self.term();
+    // `kind` only tweaks the error messages, so the same rule can parse methods later on.
+    fn function(&mut self, kind: &str) -> Result<Rc<Function>, ParseError> {
+        let name = self.consume(TokenKind::Identifier, &format!("Expected {kind} name."))?;
 
-        while self.is_not_at_end() {
-            match self.tokens[self.current] {
-                Token::Greater(lx, lt, ln) => {
-                    let operator = self.tokens[self.current].clone();
+        self.consume(
+            TokenKind::LeftParen,
+            &format!("Expected '(' after {kind} name."),
+        )?;
 
-                    self.advance();
+        let (params, body) = self.parameters_and_body(kind)?;
 
-                    expr = Expr::Binary(Box::new(Binary {
-                        left: expr,
-                        operator,
-                        right: self.term(),
-                    }))
-                }
-                Token::GreaterEqual(lx, lt, ln) => {
-                    let operator = self.tokens[self.current].clone();
+        Ok(Rc::new(Function { name, params, body }))
+    }
 
-                    self.advance();
+    // Everything after the opening parenthesis, shared by named functions,
+    // methods and lambdas.
+    fn parameters_and_body(&mut self, kind: &str) -> Result<(Vec<Token>, Vec<Stmt>), ParseError> {
+        let mut params: Vec<Token> = Vec::new();
 
-                    expr = Expr::Binary(Box::new(Binary {
-                        left: expr,
-                        operator,
-                        right: self.term(),
-                    }))
+        if !self.check(TokenKind::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    let error =
+                        self.error(&format!("Can't have more than {MAX_ARGUMENTS} parameters."));
+                    self.errors.push(error);
                 }
-                Token::Less(lx, lt, ln) => {
-                    let operator = self.tokens[self.current].clone();
 
-                    self.advance();
+                params.push(self.consume(TokenKind::Identifier, "Expected parameter name.")?);
 
-                    expr = Expr::Binary(Box::new(Binary {
-                        left: expr,
-                        operator,
-                        right: self.term(),
-                    }))
+                if !self.match_token(&[TokenKind::Comma]) {
+                    break;
                 }
-                Token::LessEqual(lx, lt, ln) => {
-                    let operator = self.tokens[self.current].clone();
+            }
+        }
 
-                    self.advance();
+        self.consume(TokenKind::RightParen, "Expected ')' after parameters.")?;
 
-                    expr = Expr::Binary(Box::new(Binary {
-                        left: expr,
-                        operator,
-                        right: self.term(),
-                    }))
-                }
-                _ => break,
-            };
+        self.consume(
+            TokenKind::LeftBrace,
+            &format!("Expected '{{' before {kind} body."),
+        )?;
+
+        let body = self.block()?;
+
+        Ok((params, body))
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume(TokenKind::Identifier, "Expected variable name.")?;
+
+        let mut initializer = None;
+
+        if self.match_token(&[TokenKind::Equal]) {
+            initializer = Some(self.expression()?);
         }
 
-        expr
+        self.consume(
+            TokenKind::Semicolon,
+            "Expected ';' after variable declaration.",
+        )?;
+
+        Ok(Stmt::Var(Var { name, initializer }))
     }
 
-    fn term(&mut self) -> Expr {
-        let mut expr = self.factor();
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        match self.peek().kind {
+            TokenKind::Print => {
+                self.advance();
+
+                self.print_statement()
+            }
+            TokenKind::Return => {
+                let keyword = self.advance().clone();
+
+                self.return_statement(keyword)
+            }
+            TokenKind::LeftBrace if !self.starts_map() => {
+                self.advance();
 
-        while self.is_not_at_end() {
-            match self.tokens[self.current] {
-                Token::Minus(lx, lt, ln) => {
-                    let operator = self.tokens[self.current].clone();
+                Ok(Stmt::Block(Block {
+                    statements: self.block()?,
+                }))
+            }
+            TokenKind::If => {
+                self.advance();
 
-                    self.advance();
+                self.if_statement()
+            }
+            TokenKind::While => {
+                self.advance();
 
-                    expr = Expr::Binary(Box::new(Binary {
-                        left: expr,
-                        operator,
-                        right: self.factor(),
-                    }))
-                }
-                Token::Plus(lx, lt, ln) => {
-                    let operator = self.tokens[self.current].clone();
+                self.while_statement()
+            }
+            TokenKind::For => {
+                self.advance();
 
-                    self.advance();
+                self.for_statement()
+            }
+            _ => self.expression_statement(),
+        }
+    }
 
-                    expr = Expr::Binary(Box::new(Binary {
-                        left: expr,
-                        operator,
-                        right: self.factor(),
-                    }))
-                }
-                _ => break,
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenKind::LeftParen, "Expected '(' after 'if'.")?;
+
+        let condition = self.expression()?;
+
+        self.consume(TokenKind::RightParen, "Expected ')' after if condition.")?;
+
+        let then_branch = self.statement()?;
+        let mut else_branch = None;
+
+        // an else binds to the nearest if, which settles the dangling else ambiguity
+        if self.match_token(&[TokenKind::Else]) {
+            else_branch = Some(self.statement()?);
+        }
+
+        Ok(Stmt::If(Box::new(If {
+            condition,
+            then_branch,
+            else_branch,
+        })))
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenKind::LeftParen, "Expected '(' after 'while'.")?;
+
+        let condition = self.expression()?;
+
+        self.consume(TokenKind::RightParen, "Expected ')' after condition.")?;
+
+        let body = self.statement()?;
+
+        Ok(Stmt::While(Box::new(While { condition, body })))
+    }
+
+    // There's no For node, the loop is desugared into a while loop wrapped in blocks.
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenKind::LeftParen, "Expected '(' after 'for'.")?;
+
+        let initializer = if self.match_token(&[TokenKind::Semicolon]) {
+            None
+        } else if self.match_token(&[TokenKind::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = match self.check(TokenKind::Semicolon) {
+            true => None,
+            false => Some(self.expression()?),
+        };
+
+        let semicolon = self.consume(TokenKind::Semicolon, "Expected ';' after loop condition.")?;
+
+        let increment = match self.check(TokenKind::RightParen) {
+            true => None,
+            false => Some(self.expression()?),
+        };
+
+        self.consume(TokenKind::RightParen, "Expected ')' after for clauses.")?;
+
+        let mut body = self.statement()?;
+
+        if let Some(increment) = increment {
+            body = Stmt::Block(Block {
+                statements: vec![
+                    body,
+                    Stmt::Expression(Expression {
+                        expression: increment,
+                    }),
+                ],
+            });
+        }
+
+        body = Stmt::While(Box::new(While {
+            condition: condition.unwrap_or(Expr::Literal(Literal {
+                value: LiteralValue::Boolean(true),
+                span: semicolon.span,
+            })),
+            body,
+        }));
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(Block {
+                statements: vec![initializer, body],
+            });
+        }
+
+        Ok(body)
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements: Vec<Stmt> = Vec::new();
+
+        while !self.check(TokenKind::RightBrace) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+
+        self.consume(TokenKind::RightBrace, "Expected '}' after block.")?;
+
+        Ok(statements)
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expression = self.expression()?;
+
+        self.consume(TokenKind::Semicolon, "Expected ';' after value.")?;
+
+        Ok(Stmt::Print(Print { expression }))
+    }
+
+    fn return_statement(&mut self, keyword: Token) -> Result<Stmt, ParseError> {
+        let value = match self.check(TokenKind::Semicolon) {
+            true => None,
+            false => Some(self.expression()?),
+        };
+
+        self.consume(TokenKind::Semicolon, "Expected ';' after return value.")?;
+
+        Ok(Stmt::Return(Return { keyword, value }))
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expression = self.expression()?;
+
+        self.consume(TokenKind::Semicolon, "Expected ';' after expression.")?;
+
+        Ok(Stmt::Expression(Expression { expression }))
+    }
+
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        self.parse_precedence(Precedence::Comma)
+    }
+
+    // Parses an expression made of operators that bind at least as tightly as
+    // `precedence`. The first token picks the prefix handler, then every
+    // following operator strong enough to bind is folded in by its infix handler.
+    fn parse_precedence(&mut self, precedence: Precedence) -> Result<Expr, ParseError> {
+        let Some(prefix) = rule(self.peek().kind).prefix else {
+            return Err(self.error("Expected expression."));
+        };
+
+        self.advance();
+
+        let mut expr = prefix(self)?;
+
+        loop {
+            let rule = rule(self.peek().kind);
+
+            let Some(infix) = rule.infix else {
+                break;
             };
+
+            if rule.precedence < precedence {
+                break;
+            }
+
+            self.advance();
+
+            expr = infix(self, expr)?;
+        }
+
+        Ok(expr)
+    }
+
+    // Parses the right operand of an infix operator at the level its rule asks for.
+    fn operand(&mut self, operator: TokenKind) -> Result<Expr, ParseError> {
+        let precedence = rule(operator).operand_precedence();
+
+        self.parse_precedence(precedence)
+    }
+
+    fn literal(&mut self) -> Result<Expr, ParseError> {
+        let token = self.previous().clone();
+
+        let value = match token.kind {
+            TokenKind::False => LiteralValue::Boolean(false),
+            TokenKind::True => LiteralValue::Boolean(true),
+            TokenKind::Nil => LiteralValue::Nil,
+            _ => return Ok(token_literal(token)),
+        };
+
+        Ok(Expr::Literal(Literal {
+            value,
+            span: token.span,
+        }))
+    }
+
+    fn variable(&mut self) -> Result<Expr, ParseError> {
+        Ok(Expr::Variable(Variable {
+            name: self.previous().clone(),
+            depth: Cell::new(None),
+        }))
+    }
+
+    fn this(&mut self) -> Result<Expr, ParseError> {
+        Ok(Expr::This(This {
+            keyword: self.previous().clone(),
+            depth: Cell::new(None),
+        }))
+    }
+
+    fn super_(&mut self) -> Result<Expr, ParseError> {
+        let keyword = self.previous().clone();
+
+        self.consume(TokenKind::Dot, "Expected '.' after 'super'.")?;
+
+        let method = self.consume(TokenKind::Identifier, "Expected superclass method name.")?;
+
+        Ok(Expr::Super(Super {
+            keyword,
+            method,
+            depth: Cell::new(None),
+        }))
+    }
+
+    fn grouping(&mut self) -> Result<Expr, ParseError> {
+        let start = self.previous().span;
+
+        let expression = self.expression()?;
+
+        let paren = self.consume(TokenKind::RightParen, "Expected ')' after expression.")?;
+
+        Ok(Expr::Grouping(Box::new(Grouping {
+            expression,
+            span: start.to(paren.span),
+        })))
+    }
+
+    fn list(&mut self) -> Result<Expr, ParseError> {
+        let start = self.previous().span;
+        let mut elements: Vec<Expr> = Vec::new();
+
+        // a trailing comma is fine, so every line of a multi-line list can end in one
+        while !self.check(TokenKind::RightBracket) {
+            elements.push(self.parse_precedence(Precedence::Assignment)?);
+
+            if !self.match_token(&[TokenKind::Comma]) {
+                break;
+            }
         }
 
-        expr
+        let bracket = self.consume(TokenKind::RightBracket, "Expected ']' after list elements.")?;
+
+        Ok(Expr::List(List {
+            elements,
+            span: start.to(bracket.span),
+        }))
+    }
+
+    fn map(&mut self) -> Result<Expr, ParseError> {
+        let start = self.previous().span;
+        let mut entries: Vec<Entry> = Vec::new();
+
+        // trailing commas are allowed here too, same as in lists
+        while !self.check(TokenKind::RightBrace) {
+            let key = self.parse_precedence(Precedence::Assignment)?;
+            let colon = self.consume(TokenKind::Colon, "Expected ':' after map key.")?;
+            let value = self.parse_precedence(Precedence::Assignment)?;
+
+            entries.push(Entry { key, colon, value });
+
+            if !self.match_token(&[TokenKind::Comma]) {
+                break;
+            }
+        }
+
+        let brace = self.consume(TokenKind::RightBrace, "Expected '}' after map entries.")?;
+
+        Ok(Expr::Map(Map {
+            entries,
+            span: start.to(brace.span),
+        }))
     }
 
-    fn factor(&mut self) -> Expr {
-        let mut expr = self.unary();
+    fn lambda(&mut self) -> Result<Expr, ParseError> {
+        let start = self.previous().span;
 
-        while self.is_not_at_end() {
-            match self.tokens[self.current] {
-                Token::Slash(lx, lt, ln) => {
-                    let operator = self.tokens[self.current].clone();
+        self.consume(TokenKind::LeftParen, "Expected '(' after 'fun'.")?;
+
+        let (params, body) = self.parameters_and_body("function")?;
+        let span = start.to(self.previous().span);
+
+        Ok(Expr::Lambda(Rc::new(Lambda { params, body, span })))
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        Ok(Expr::Unary(Box::new(Unary {
+            operator: self.previous().clone(),
+            right: self.parse_precedence(Precedence::Unary)?,
+        })))
+    }
+
+    // Error production for a binary operator with nothing on its left, like `* 3`.
+    // The right operand is parsed as if the left one were there and thrown away,
+    // so the rest of the statement still gets checked for errors.
+    fn missing_left_operand(&mut self) -> Result<Expr, ParseError> {
+        let operator = self.previous().clone();
+
+        self.errors.push(ParseError {
+            message: format!("Missing left-hand operand for '{operator}'."),
+            span: operator.span,
+            help: None,
+        });
+
+        let right = self.operand(operator.kind)?;
+
+        Ok(Expr::Literal(Literal {
+            value: LiteralValue::Nil,
+            span: operator.span.to(right.span()),
+        }))
+    }
+
+    fn binary(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let operator = self.previous().clone();
+        let right = self.operand(operator.kind)?;
+
+        Ok(Expr::Binary(Box::new(Binary {
+            left,
+            operator,
+            right,
+        })))
+    }
+
+    fn logical(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let operator = self.previous().clone();
+        let right = self.operand(operator.kind)?;
+
+        Ok(Expr::Logical(Box::new(Logical {
+            left,
+            operator,
+            right,
+        })))
+    }
+
+    fn comma(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        Ok(Expr::Comma(Box::new(Comma {
+            left,
+            right: self.operand(TokenKind::Comma)?,
+        })))
+    }
 
-                    self.advance();
+    fn assignment(&mut self, target: Expr) -> Result<Expr, ParseError> {
+        let value = self.operand(TokenKind::Equal)?;
 
-                    expr = Expr::Binary(Box::new(Binary {
-                        left: expr,
-                        operator,
-                        right: self.unary(),
-                    }))
+        match target {
+            Expr::Variable(variable) => {
+                return Ok(Expr::Assign(Box::new(Assign {
+                    name: variable.name,
+                    value,
+                    depth: Cell::new(None),
+                })));
+            }
+            Expr::Get(get) => {
+                return Ok(Expr::Set(Box::new(Set {
+                    object: get.object,
+                    name: get.name,
+                    value,
+                })));
+            }
+            Expr::Index(index) => {
+                return Ok(Expr::IndexSet(Box::new(IndexSet {
+                    object: index.object,
+                    bracket: index.bracket,
+                    index: index.index,
+                    value,
+                })));
+            }
+            _ => {}
+        }
+
+        // the parser isn't confused about where it is, so there's no need to synchronize
+        self.errors.push(ParseError {
+            message: "Invalid assignment target.".to_owned(),
+            span: target.span(),
+            help: Some(
+                "only variables, properties, list elements and map entries can be assigned to",
+            ),
+        });
+
+        Ok(target)
+    }
+
+    // Like C, the middle operand can be any expression since the `:` closes it
+    // off, while the last one is parsed at the conditional's own level so
+    // `a ? b : c ? d : e` nests to the right.
+    fn conditional(&mut self, condition: Expr) -> Result<Expr, ParseError> {
+        let then_branch = self.expression()?;
+
+        self.consume(
+            TokenKind::Colon,
+            "Expected ':' after then branch of conditional expression.",
+        )?;
+
+        Ok(Expr::Conditional(Box::new(Conditional {
+            condition,
+            then_branch,
+            else_branch: self.operand(TokenKind::Question)?,
+        })))
+    }
+
+    fn get(&mut self, object: Expr) -> Result<Expr, ParseError> {
+        let name = self.consume(TokenKind::Identifier, "Expected property name after '.'.")?;
+
+        Ok(Expr::Get(Box::new(Get { object, name })))
+    }
+
+    fn index(&mut self, object: Expr) -> Result<Expr, ParseError> {
+        let index = self.expression()?;
+
+        let bracket = self.consume(TokenKind::RightBracket, "Expected ']' after index.")?;
+
+        Ok(Expr::Index(Box::new(Index {
+            object,
+            bracket,
+            index,
+        })))
+    }
+
+    fn call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        let mut arguments: Vec<Expr> = Vec::new();
+
+        if !self.check(TokenKind::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    // report without bailing out, the parser is still in a sane state
+                    let error =
+                        self.error(&format!("Can't have more than {MAX_ARGUMENTS} arguments."));
+                    self.errors.push(error);
                 }
-                Token::Star(lx, lt, ln) => {
-                    let operator = self.tokens[self.current].clone();
 
-                    self.advance();
+                // a comma here separates arguments, so start just above it
+                arguments.push(self.parse_precedence(Precedence::Assignment)?);
 
-                    expr = Expr::Binary(Box::new(Binary {
-                        left: expr,
-                        operator,
-                        right: self.unary(),
-                    }))
+                if !self.match_token(&[TokenKind::Comma]) {
+                    break;
                 }
-                _ => break,
-            };
+            }
         }
 
-        expr
+        let paren = self.consume(TokenKind::RightParen, "Expected ')' after arguments.")?;
+
+        Ok(Expr::Call(Box::new(Call {
+            callee,
+            paren,
+            arguments,
+        })))
     }
 
-    fn unary(&mut self) -> Expr {
-        match self.tokens[self.current] {
-            Token::Bang(lx, lt, ln) => {
-                let operator = self.tokens[self.current].clone();
+    // `"a ${b} c"` is kept as the pieces `"a "`, `b` and `" c"`, which get
+    // stringified and joined at runtime.
+    fn interpolation(&mut self) -> Result<Expr, ParseError> {
+        let mut part = self.previous().clone();
+        let span = part.span;
+        let mut parts: Vec<Expr> = Vec::new();
 
-                self.advance();
+        loop {
+            let kind = part.kind;
+            let end = part.span;
 
-                let expr = Expr::Unary(Box::new(Unary {
-                    operator,
-                    right: self.unary(),
-                }));
+            // nothing to gain from keeping empty pieces like the one in `"${a}${b}"`
+            if part.literal != Some(TokenLiteral::String(String::new())) {
+                parts.push(token_literal(part));
+            }
 
-                return expr;
+            if kind == TokenKind::InterpolationEnd {
+                return Ok(Expr::Interpolation(Interpolation {
+                    parts,
+                    span: span.to(end),
+                }));
             }
-            Token::Minus(lx, lt, ln) => {
-                let operator = self.tokens[self.current].clone();
 
-                self.advance();
+            parts.push(self.expression()?);
 
-                return Expr::Unary(Box::new(Unary {
-                    operator,
-                    right: self.unary(),
-                }));
+            if !self.match_token(&[TokenKind::InterpolationMiddle, TokenKind::InterpolationEnd]) {
+                return Err(self.error("Expected '}' after interpolated expression."));
             }
-            _ => {}
+
+            part = self.previous().clone();
+        }
+    }
+
+    fn consume(&mut self, kind: TokenKind, message: &str) -> Result<Token, ParseError> {
+        if self.check(kind) {
+            return Ok(self.advance().clone());
         }
 
-        self.primary()
+        Err(self.error(message))
     }
 
-    fn primary(&mut self) -> Expr {
-        let token = self.tokens[self.current].clone();
+    fn error(&self, message: &str) -> ParseError {
+        let token = self.peek();
 
-        self.advance();
+        if token.kind != TokenKind::Eof || self.current == 0 {
+            return self.error_at(token, message);
+        }
+
+        // point just past the last token, where the missing input should have been,
+        // rather than at whatever trailing whitespace the source ends with
+        let last = self.previous();
+
+        // a multi-line string ends on a later line than it starts
+        let (line, column) = match last.lexeme.rfind('\n') {
+            Some(newline) => (
+                last.span.line + last.lexeme.matches('\n').count(),
+                last.lexeme[newline + 1..].chars().count() + 1,
+            ),
+            None => (
+                last.span.line,
+                last.span.column + last.lexeme.chars().count(),
+            ),
+        };
 
-        match token {
-            Token::False(lx, lt, ln) => Expr::Literal(Literal::Boolean(false)),
-            Token::True(lx, lt, ln) => Expr::Literal(Literal::Boolean(true)),
-            Token::Nil(lx, lt, ln) => Expr::Literal(Literal::Nil),
-            Token::Number(lx, lt, ln) => Expr::Literal(Literal::Number(lt)),
-            Token::String(lx, lt, ln) => Expr::Literal(Literal::String(lt)),
-            Token::LeftParen(lx, lt, ln) => {
-                let expr = self.expression();
-
-                match self.tokens[self.current] {
-                    Token::RightParen(lx, lt, ln) => self.advance(),
-                    _ => panic!("Expected ')' after expression started at line {ln}"),
+        let eof = Token::eof(Span {
+            line,
+            column,
+            start: last.span.end,
+            ..last.span
+        });
+
+        self.error_at(&eof, message)
+    }
+
+    fn error_at(&self, token: &Token, message: &str) -> ParseError {
+        let (found, span) = match token.kind {
+            TokenKind::Eof => (token.to_string(), token.span),
+            // a string resuming after `${...}` starts with the `}` that closed it,
+            // which is the part actually out of place
+            TokenKind::InterpolationMiddle | TokenKind::InterpolationEnd => {
+                let brace = Span {
+                    end: token.span.start + 1,
+                    ..token.span
                 };
 
-                Expr::Grouping(Box::new(Grouping { expression: expr }))
+                ("token \"}\"".to_owned(), brace)
+            }
+            // the lexeme already comes with its quotes
+            TokenKind::String | TokenKind::Interpolation => {
+                (format!("{} {token}", token.kind), token.span)
             }
-            _ => {
-                let (lx, lt, ln) = token.get_info();
+            _ => (format!("token \"{token}\""), token.span),
+        };
+
+        ParseError {
+            message: format!("Unexpected {found}. {message}"),
+            span,
+            help: None,
+        }
+    }
+
+    // Discard tokens until we are likely at the start of a new statement, so
+    // one syntax error doesn't cascade into a pile of bogus ones.
+    fn synchronize(&mut self) {
+        self.advance();
 
-                panic!("Unexpected token: \"{lx}\" at line {ln}");
+        while !self.is_at_end() {
+            if self.previous().kind == TokenKind::Semicolon {
+                return;
             }
+
+            match self.peek().kind {
+                TokenKind::Class
+                | TokenKind::Fun
+                | TokenKind::Var
+                | TokenKind::For
+                | TokenKind::If
+                | TokenKind::While
+                | TokenKind::Print
+                | TokenKind::Return => return,
+                _ => {}
+            };
+
+            self.advance();
         }
     }
 
-    fn advance(&mut self) {
-        if self.is_not_at_end() {
+    // Consumes the current token if it is any of `kinds`.
+    fn match_token(&mut self, kinds: &[TokenKind]) -> bool {
+        if kinds.iter().any(|&kind| self.check(kind)) {
+            self.advance();
+
+            return true;
+        }
+
+        false
+    }
+    fn check(&self, kind: TokenKind) -> bool {
+        self.peek().kind == kind
+    }
+    // Moves past the current token and returns it. The trailing Eof is never consumed.
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
             self.current += 1;
         }
+
+        self.previous()
+    }
+    fn check_next(&self, kind: TokenKind) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|token| token.kind == kind)
+    }
+    // A `{` starting a statement opens a block, unless a single token and a `:`
+    // follow it like in `{"a": 1}`. No statement inside a block can start that
+    // way, so the lookahead never mistakes one for the other. Empty braces and
+    // maps with longer keys are blocks here, but work fine in expressions.
+    fn starts_map(&self) -> bool {
+        self.tokens
+            .get(self.current + 2)
+            .is_some_and(|token| token.kind == TokenKind::Colon)
+    }
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
     }
     fn is_at_end(&self) -> bool {
-        self.current >= (self.tokens.len() - 1)
+        self.peek().kind == TokenKind::Eof
+    }
+}
+
+fn token_literal(token: Token) -> Expr {
+    let value = match token.literal {
+        Some(TokenLiteral::Number(number)) => LiteralValue::Number(number),
+        Some(TokenLiteral::String(string)) => LiteralValue::String(string),
+        None => unreachable!("literal tokens always carry a value"),
+    };
+
+    Expr::Literal(Literal {
+        value,
+        span: token.span,
+    })
+}
+
+// The expression grammar. Adding an operator means adding its token here.
+fn rule(kind: TokenKind) -> Rule {
+    use Associativity::{Left, Right};
+    use Precedence as P;
+
+    let (prefix, infix, precedence, associativity): (Option<PrefixFn>, Option<InfixFn>, _, _) =
+        match kind {
+            TokenKind::False
+            | TokenKind::True
+            | TokenKind::Nil
+            | TokenKind::Number
+            | TokenKind::String => (Some(Parser::literal), None, P::None, Left),
+            TokenKind::Interpolation => (Some(Parser::interpolation), None, P::None, Left),
+            TokenKind::Identifier => (Some(Parser::variable), None, P::None, Left),
+            TokenKind::This => (Some(Parser::this), None, P::None, Left),
+            TokenKind::Super => (Some(Parser::super_), None, P::None, Left),
+            TokenKind::Fun => (Some(Parser::lambda), None, P::None, Left),
+            TokenKind::Bang | TokenKind::Tilde => (Some(Parser::unary), None, P::None, Left),
+
+            // the lowest level of all, so anywhere a comma already means something
+            // else, like between call arguments, parsing starts above it
+            TokenKind::Comma => (None, Some(Parser::comma), P::Comma, Left),
+            TokenKind::Equal => (None, Some(Parser::assignment), P::Assignment, Right),
+            TokenKind::Question => (None, Some(Parser::conditional), P::Conditional, Right),
+            TokenKind::Or => (
+                Some(Parser::missing_left_operand),
+                Some(Parser::logical),
+                P::Or,
+                Left,
+            ),
+            TokenKind::And => (
+                Some(Parser::missing_left_operand),
+                Some(Parser::logical),
+                P::And,
+                Left,
+            ),
+            TokenKind::BangEqual | TokenKind::EqualEqual => (
+                Some(Parser::missing_left_operand),
+                Some(Parser::binary),
+                P::Equality,
+                Left,
+            ),
+            TokenKind::Greater
+            | TokenKind::GreaterEqual
+            | TokenKind::Less
+            | TokenKind::LessEqual => (
+                Some(Parser::missing_left_operand),
+                Some(Parser::binary),
+                P::Comparison,
+                Left,
+            ),
+
+            // the bitwise operators sit between comparison and arithmetic like in
+            // Python, so `a & 1 == 0` compares the masked value rather than masking
+            // a boolean
+            TokenKind::Pipe => (
+                Some(Parser::missing_left_operand),
+                Some(Parser::binary),
+                P::BitwiseOr,
+                Left,
+            ),
+            TokenKind::Caret => (
+                Some(Parser::missing_left_operand),
+                Some(Parser::binary),
+                P::BitwiseXor,
+                Left,
+            ),
+            TokenKind::Ampersand => (
+                Some(Parser::missing_left_operand),
+                Some(Parser::binary),
+                P::BitwiseAnd,
+                Left,
+            ),
+            TokenKind::LessLess | TokenKind::GreaterGreater => (
+                Some(Parser::missing_left_operand),
+                Some(Parser::binary),
+                P::Shift,
+                Left,
+            ),
+            TokenKind::Plus => (
+                Some(Parser::missing_left_operand),
+                Some(Parser::binary),
+                P::Term,
+                Left,
+            ),
+            // `-` is also negation, so it never needs the missing operand error
+            TokenKind::Minus => (Some(Parser::unary), Some(Parser::binary), P::Term, Left),
+            TokenKind::Slash | TokenKind::Star | TokenKind::Percent => (
+                Some(Parser::missing_left_operand),
+                Some(Parser::binary),
+                P::Factor,
+                Left,
+            ),
+
+            // `**` binds tighter than the prefix operators on its left but its right
+            // operand can still start with one, the same rule as Python and maths
+            // notation: `-2 ** 2` is `-(2 ** 2)`, `2 ** -1` is `2 ** (-1)`, and
+            // `2 ** 3 ** 2` is `2 ** (3 ** 2)`
+            TokenKind::StarStar => (
+                Some(Parser::missing_left_operand),
+                Some(Parser::binary),
+                P::Power,
+                Right,
+            ),
+            TokenKind::LeftParen => (Some(Parser::grouping), Some(Parser::call), P::Call, Left),
+            TokenKind::LeftBracket => (Some(Parser::list), Some(Parser::index), P::Call, Left),
+            TokenKind::LeftBrace => (Some(Parser::map), None, P::None, Left),
+            TokenKind::Dot => (None, Some(Parser::get), P::Call, Left),
+            _ => (None, None, P::None, Left),
+        };
+
+    Rule {
+        prefix,
+        infix,
+        precedence,
+        associativity,
     }
-    fn is_not_at_end(&self) -> bool {
-        self.current < self.tokens.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Ast;
+    use crate::scanner::Scanner;
+
+    // Parses `source` and prints every statement the way `--ast` does, along
+    // with the messages of any parse errors.
+    fn parse(source: &str) -> (Vec<String>, Vec<String>) {
+        let (tokens, scan_errors) = Scanner::from(source.to_owned()).scan_tokens();
+
+        assert!(scan_errors.is_empty(), "{source:?} failed to scan");
+
+        let (statements, errors) = Parser::new(tokens).parse();
+        let ast = Ast::new(Vec::new());
+
+        (
+            statements
+                .iter()
+                .map(|statement| statement.accept(&ast))
+                .collect(),
+            errors.into_iter().map(|error| error.message).collect(),
+        )
+    }
+
+    fn assert_parses(source: &str, expected: &str) {
+        let (statements, errors) = parse(source);
+
+        assert!(errors.is_empty(), "{source:?} failed to parse: {errors:?}");
+        assert_eq!(statements, [expected], "{source:?}");
+    }
+
+    #[test]
+    fn power_binds_tighter_than_negation() {
+        assert_parses("-2 ** 2;", "(; -(** 2 2))");
+        assert_parses("2 ** -1;", "(; (** 2 -1))");
+    }
+
+    #[test]
+    fn power_is_right_associative() {
+        assert_parses("2 ** 3 ** 2;", "(; (** 2 (** 3 2)))");
+    }
+
+    #[test]
+    fn arithmetic_is_left_associative() {
+        assert_parses("1 - 2 - 3;", "(; (- (- 1 2) 3))");
+        assert_parses("1 + 2 * 3;", "(; (+ 1 (* 2 3)))");
+    }
+
+    #[test]
+    fn conditional_nests_to_the_right() {
+        assert_parses("a ? b : c ? d : e;", "(; (?: a b (?: c d e)))");
+    }
+
+    #[test]
+    fn assignment_is_right_associative() {
+        assert_parses("a = b = c;", "(; (= a (= b c)))");
+    }
+
+    #[test]
+    fn commas_inside_call_arguments_separate_them() {
+        assert_parses("f(a, b), c;", "(; (, (call f a b) c))");
+    }
+
+    #[test]
+    fn reports_invalid_assignment_target() {
+        let (statements, errors) = parse("1 + 2 = 3;");
+
+        assert_eq!(statements, ["(; (+ 1 2))"]);
+        assert_eq!(errors, ["Invalid assignment target."]);
+    }
+
+    #[test]
+    fn reports_missing_left_operand() {
+        let (statements, errors) = parse("* 3;");
+
+        assert_eq!(statements, ["(; nil)"]);
+        assert_eq!(errors, ["Missing left-hand operand for '*'."]);
+    }
+
+    #[test]
+    fn interpolation_keeps_its_pieces_in_order() {
+        assert_parses(
+            r#"print "a${1}b${x}";"#,
+            r#"(print (interpolate "a" 1 "b" x))"#,
+        );
+    }
+
+    #[test]
+    fn interpolated_operand_cant_take_the_rest_of_the_string() {
+        let (_, errors) = parse(r#"var x = "a"; print "${x +}" "y";"#);
+
+        assert_eq!(errors, [r#"Unexpected token "}". Expected expression."#]);
+    }
+
+    #[test]
+    fn rejects_empty_interpolation() {
+        for source in [r#"print "a${}b" "c";"#, r#"print "${}";"#] {
+            let (_, errors) = parse(source);
+
+            assert_eq!(
+                errors,
+                [r#"Unexpected token "}". Expected expression."#],
+                "{source:?}"
+            );
+        }
     }
 }
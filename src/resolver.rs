@@ -0,0 +1,412 @@
+use crate::expr::*;
+use crate::stmt::*;
+use crate::token::*;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub struct ResolveError {
+    pub message: String,
+    pub span: Span,
+    pub help: Option<&'static str>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+// Static pass run between parsing and interpreting. It works out how many
+// scopes separate every variable use from its declaration and stores that
+// on the node, so closures keep seeing the variable they captured even if
+// a same-named one is declared later in an enclosing scope.
+pub struct Resolver {
+    // the bool tracks whether the variable's initializer has finished resolving
+    scopes: RefCell<Vec<HashMap<String, bool>>>,
+    current_function: Cell<FunctionType>,
+    current_class: Cell<ClassType>,
+    errors: RefCell<Vec<ResolveError>>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: RefCell::new(Vec::new()),
+            current_function: Cell::new(FunctionType::None),
+            current_class: Cell::new(ClassType::None),
+            errors: RefCell::new(Vec::new()),
+        }
+    }
+    pub fn resolve(&self, statements: &[Stmt]) -> Vec<ResolveError> {
+        for statement in statements {
+            statement.accept(self);
+        }
+
+        self.errors.take()
+    }
+    fn begin_scope(&self) {
+        self.scopes.borrow_mut().push(HashMap::new());
+    }
+    fn end_scope(&self) {
+        self.scopes.borrow_mut().pop();
+    }
+    fn declare(&self, name: &Token) {
+        let lexeme = name.lexeme.clone();
+        let mut scopes = self.scopes.borrow_mut();
+
+        // globals aren't tracked, they're looked up dynamically
+        let Some(scope) = scopes.last_mut() else {
+            return;
+        };
+
+        if scope.contains_key(&lexeme) {
+            self.error(name, "Already a variable with this name in this scope.");
+        }
+
+        scope.insert(lexeme, false);
+    }
+    fn define(&self, name: &Token) {
+        self.define_name(&name.lexeme);
+    }
+    fn define_name(&self, name: &str) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(name.to_owned(), true);
+        }
+    }
+    fn resolve_local(&self, name: &Token, depth: &Cell<Option<usize>>) {
+        let lexeme = &name.lexeme;
+
+        // not found in any scope means it's a global and depth stays None
+        for (distance, scope) in self.scopes.borrow().iter().rev().enumerate() {
+            if scope.contains_key(lexeme) {
+                depth.set(Some(distance));
+                return;
+            }
+        }
+    }
+    // Takes the parameters and body rather than a `Function` so lambdas can share it.
+    fn resolve_function(&self, params: &[Token], body: &[Stmt], function_type: FunctionType) {
+        let enclosing_function = self.current_function.replace(function_type);
+
+        self.begin_scope();
+
+        for param in params {
+            self.declare(param);
+            self.define(param);
+        }
+
+        for statement in body {
+            statement.accept(self);
+        }
+
+        self.end_scope();
+        self.current_function.set(enclosing_function);
+    }
+    fn error(&self, token: &Token, message: &str) {
+        self.error_with_help(token, message, None);
+    }
+
+    fn error_with_help(&self, token: &Token, message: &str, help: Option<&'static str>) {
+        self.errors.borrow_mut().push(ResolveError {
+            message: message.to_owned(),
+            span: token.span,
+            help,
+        });
+    }
+}
+
+impl StmtVisitor for Resolver {
+    type Result = ();
+
+    fn visit_stmt(&self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(block) => self.visit_block_stmt(block),
+            Stmt::Class(class) => self.visit_class_stmt(class),
+            Stmt::Expression(expression) => self.visit_expression_stmt(expression),
+            Stmt::Function(function) => self.visit_function_stmt(function),
+            Stmt::If(if_stmt) => self.visit_if_stmt(if_stmt),
+            Stmt::Print(print) => self.visit_print_stmt(print),
+            Stmt::Return(return_stmt) => self.visit_return_stmt(return_stmt),
+            Stmt::Var(var) => self.visit_var_stmt(var),
+            Stmt::While(while_stmt) => self.visit_while_stmt(while_stmt),
+        }
+    }
+
+    fn visit_block_stmt(&self, stmt: &Block) {
+        self.begin_scope();
+
+        for statement in &stmt.statements {
+            statement.accept(self);
+        }
+
+        self.end_scope();
+    }
+
+    fn visit_class_stmt(&self, stmt: &Class) {
+        let enclosing_class = self.current_class.replace(ClassType::Class);
+
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+
+        if let Some(superclass) = &stmt.superclass {
+            if stmt.name.lexeme == superclass.name.lexeme {
+                self.error(&superclass.name, "A class can't inherit from itself.");
+            }
+
+            self.current_class.set(ClassType::Subclass);
+            self.visit_variable_expr(superclass);
+
+            // mirrors the extra environment the interpreter creates for `super`
+            self.begin_scope();
+            self.define_name("super");
+        }
+
+        self.begin_scope();
+        self.define_name("this");
+
+        for method in &stmt.methods {
+            let function_type = if method.name.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+
+            self.resolve_function(&method.params, &method.body, function_type);
+        }
+
+        self.end_scope();
+
+        if stmt.superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class.set(enclosing_class);
+    }
+
+    fn visit_expression_stmt(&self, stmt: &Expression) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_function_stmt(&self, stmt: &Rc<Function>) {
+        // defined before the body so the function can call itself recursively
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+
+        self.resolve_function(&stmt.params, &stmt.body, FunctionType::Function);
+    }
+
+    fn visit_if_stmt(&self, stmt: &If) {
+        stmt.condition.accept(self);
+        stmt.then_branch.accept(self);
+
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_print_stmt(&self, stmt: &Print) {
+        stmt.expression.accept(self);
+    }
+
+    fn visit_return_stmt(&self, stmt: &Return) {
+        if self.current_function.get() == FunctionType::None {
+            self.error(&stmt.keyword, "Can't return from top-level code.");
+        }
+
+        if let Some(value) = &stmt.value {
+            if self.current_function.get() == FunctionType::Initializer {
+                self.error_with_help(
+                    &stmt.keyword,
+                    "Can't return a value from an initializer.",
+                    Some("an initializer always returns 'this', use a bare 'return;'"),
+                );
+            }
+
+            value.accept(self);
+        }
+    }
+
+    fn visit_var_stmt(&self, stmt: &Var) {
+        self.declare(&stmt.name);
+
+        if let Some(initializer) = &stmt.initializer {
+            initializer.accept(self);
+        }
+
+        self.define(&stmt.name);
+    }
+
+    fn visit_while_stmt(&self, stmt: &While) {
+        stmt.condition.accept(self);
+        stmt.body.accept(self);
+    }
+}
+
+impl Visitor for Resolver {
+    type Result = ();
+
+    fn visit_expr(&self, expr: &Expr) {
+        match expr {
+            Expr::Assign(assign) => self.visit_assign_expr(assign),
+            Expr::Binary(binary) => self.visit_binary_expr(binary),
+            Expr::Call(call) => self.visit_call_expr(call),
+            Expr::Comma(comma) => self.visit_comma_expr(comma),
+            Expr::Conditional(conditional) => self.visit_conditional_expr(conditional),
+            Expr::Get(get) => self.visit_get_expr(get),
+            Expr::Grouping(grouping) => self.visit_grouping_expr(grouping),
+            Expr::Index(index) => self.visit_index_expr(index),
+            Expr::IndexSet(index_set) => self.visit_index_set_expr(index_set),
+            Expr::Interpolation(interpolation) => self.visit_interpolation_expr(interpolation),
+            Expr::Lambda(lambda) => self.visit_lambda_expr(lambda),
+            Expr::List(list) => self.visit_list_expr(list),
+            Expr::Literal(literal) => self.visit_literal_expr(literal),
+            Expr::Logical(logical) => self.visit_logical_expr(logical),
+            Expr::Map(map) => self.visit_map_expr(map),
+            Expr::Set(set) => self.visit_set_expr(set),
+            Expr::Super(super_expr) => self.visit_super_expr(super_expr),
+            Expr::This(this) => self.visit_this_expr(this),
+            Expr::Unary(unary) => self.visit_unary_expr(unary),
+            Expr::Variable(variable) => self.visit_variable_expr(variable),
+        }
+    }
+
+    fn visit_assign_expr(&self, expr: &Assign) {
+        expr.value.accept(self);
+        self.resolve_local(&expr.name, &expr.depth);
+    }
+
+    fn visit_binary_expr(&self, expr: &Binary) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_call_expr(&self, expr: &Call) {
+        expr.callee.accept(self);
+
+        for argument in &expr.arguments {
+            argument.accept(self);
+        }
+    }
+
+    fn visit_comma_expr(&self, expr: &Comma) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_conditional_expr(&self, expr: &Conditional) {
+        expr.condition.accept(self);
+        expr.then_branch.accept(self);
+        expr.else_branch.accept(self);
+    }
+
+    fn visit_get_expr(&self, expr: &Get) {
+        // properties are looked up dynamically, only the object needs resolving
+        expr.object.accept(self);
+    }
+
+    fn visit_grouping_expr(&self, expr: &Grouping) {
+        expr.expression.accept(self);
+    }
+
+    fn visit_index_expr(&self, expr: &Index) {
+        expr.object.accept(self);
+        expr.index.accept(self);
+    }
+
+    fn visit_index_set_expr(&self, expr: &IndexSet) {
+        expr.value.accept(self);
+        expr.object.accept(self);
+        expr.index.accept(self);
+    }
+
+    fn visit_interpolation_expr(&self, expr: &Interpolation) {
+        for part in &expr.parts {
+            part.accept(self);
+        }
+    }
+
+    fn visit_lambda_expr(&self, expr: &Rc<Lambda>) {
+        self.resolve_function(&expr.params, &expr.body, FunctionType::Function);
+    }
+
+    fn visit_list_expr(&self, expr: &List) {
+        for element in &expr.elements {
+            element.accept(self);
+        }
+    }
+
+    fn visit_literal_expr(&self, _expr: &Literal) {}
+
+    fn visit_logical_expr(&self, expr: &Logical) {
+        expr.left.accept(self);
+        expr.right.accept(self);
+    }
+
+    fn visit_map_expr(&self, expr: &Map) {
+        for entry in &expr.entries {
+            entry.key.accept(self);
+            entry.value.accept(self);
+        }
+    }
+
+    fn visit_set_expr(&self, expr: &Set) {
+        expr.value.accept(self);
+        expr.object.accept(self);
+    }
+
+    fn visit_super_expr(&self, expr: &Super) {
+        match self.current_class.get() {
+            ClassType::None => self.error(&expr.keyword, "Can't use 'super' outside of a class."),
+            ClassType::Class => self.error(
+                &expr.keyword,
+                "Can't use 'super' in a class with no superclass.",
+            ),
+            ClassType::Subclass => {}
+        }
+
+        self.resolve_local(&expr.keyword, &expr.depth);
+    }
+
+    fn visit_this_expr(&self, expr: &This) {
+        if self.current_class.get() == ClassType::None {
+            self.error(&expr.keyword, "Can't use 'this' outside of a class.");
+            return;
+        }
+
+        self.resolve_local(&expr.keyword, &expr.depth);
+    }
+
+    fn visit_unary_expr(&self, expr: &Unary) {
+        expr.right.accept(self);
+    }
+
+    fn visit_variable_expr(&self, expr: &Variable) {
+        let name = expr.name.lexeme.clone();
+
+        let declared_but_undefined = self
+            .scopes
+            .borrow()
+            .last()
+            .is_some_and(|scope| scope.get(&name) == Some(&false));
+
+        if declared_but_undefined {
+            self.error_with_help(
+                &expr.name,
+                "Can't read local variable in its own initializer.",
+                Some("to shadow an outer variable, give the new one a different name"),
+            );
+        }
+
+        self.resolve_local(&expr.name, &expr.depth);
+    }
+}
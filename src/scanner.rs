@@ -1,15 +1,46 @@
-use crate::token::Token;
+use crate::token::{Span, Token, TokenKind};
 
 pub struct ScanError {
     pub message: String,
-    pub line: usize,
+    pub span: Span,
+    pub help: Option<&'static str>,
+}
+
+// A message and help note, turned into a ScanError once the whole literal is scanned.
+type NumberError = (&'static str, &'static str);
+
+enum DigitsError {
+    Missing,
+    MisplacedSeparator,
+}
+
+impl DigitsError {
+    // What went wrong, with `missing` describing where the digits should have been.
+    fn explain(self, missing: NumberError) -> NumberError {
+        match self {
+            DigitsError::Missing => missing,
+            DigitsError::MisplacedSeparator => (
+                "Misplaced digit separator.",
+                "'_' can only go between two digits, like in 1_000",
+            ),
+        }
+    }
 }
 
 pub struct Scanner {
     source: Vec<char>,
     current: usize,
+    // byte offset of `current` in the original string, chars can be wider than one byte
+    offset: usize,
     line: usize,
-    errors: Vec<ScanError>, // TODO: actually collect errors
+    // index of the first char of the current line, used to work out columns
+    line_start: usize,
+    // where the token being scanned began
+    start: Span,
+    // one entry per `${` we're inside of, counting the braces opened since, so
+    // we know which `}` closes the interpolation and resumes the string
+    interpolations: Vec<usize>,
+    errors: Vec<ScanError>,
 }
 
 impl Scanner {
@@ -17,22 +48,55 @@ impl Scanner {
         Scanner {
             source: source.chars().collect(),
             current: 0,
+            offset: 0,
             line: 1,
+            line_start: 0,
+            start: Span {
+                line: 1,
+                column: 1,
+                start: 0,
+                end: 0,
+            },
+            interpolations: Vec::new(),
             errors: Vec::new(),
         }
     }
-    pub fn scan_tokens(&mut self) -> Vec<Token> {
+    pub fn scan_tokens(&mut self) -> (Vec<Token>, Vec<ScanError>) {
         let mut tokens: Vec<Token> = Vec::new();
 
         while self.is_not_at_end() {
             let c = self.get_current_char();
 
-            //  TODO: Add powers **, %, and bitwise operators
+            self.start = self.position();
+
             match c {
                 '(' => tokens.push(self.scan_symbol(1)),
                 ')' => tokens.push(self.scan_symbol(1)),
-                '{' => tokens.push(self.scan_symbol(1)),
-                '}' => tokens.push(self.scan_symbol(1)),
+                '{' => {
+                    if let Some(depth) = self.interpolations.last_mut() {
+                        *depth += 1;
+                    }
+
+                    tokens.push(self.scan_symbol(1));
+                }
+                '}' if self.interpolations.last() == Some(&0) => {
+                    let start = self.current;
+
+                    self.interpolations.pop();
+                    self.advance(); // the } isn't a token, the string just carries on
+
+                    match self.scan_string_part(start, true) {
+                        Ok(token) => tokens.push(token),
+                        Err(error) => self.errors.push(error),
+                    }
+                }
+                '}' => {
+                    if let Some(depth) = self.interpolations.last_mut() {
+                        *depth -= 1;
+                    }
+
+                    tokens.push(self.scan_symbol(1));
+                }
                 '[' => tokens.push(self.scan_symbol(1)),
                 ']' => tokens.push(self.scan_symbol(1)),
                 ',' => tokens.push(self.scan_symbol(1)),
@@ -40,47 +104,98 @@ impl Scanner {
                 '-' => tokens.push(self.scan_symbol(1)),
                 '+' => tokens.push(self.scan_symbol(1)),
                 ';' => tokens.push(self.scan_symbol(1)),
+                '*' if self.peek() == '*' => tokens.push(self.scan_symbol(2)),
                 '*' => tokens.push(self.scan_symbol(1)),
+                '%' => tokens.push(self.scan_symbol(1)),
+                '&' => tokens.push(self.scan_symbol(1)),
+                '|' => tokens.push(self.scan_symbol(1)),
+                '^' => tokens.push(self.scan_symbol(1)),
+                '~' => tokens.push(self.scan_symbol(1)),
+                '?' => tokens.push(self.scan_symbol(1)),
+                ':' => tokens.push(self.scan_symbol(1)),
                 ' ' | '\r' | '\t' => self.advance(),
                 '!' if self.peek() == '=' => tokens.push(self.scan_symbol(2)),
                 '!' => tokens.push(self.scan_symbol(1)),
                 '=' if self.peek() == '=' => tokens.push(self.scan_symbol(2)),
                 '=' => tokens.push(self.scan_symbol(1)),
                 '<' if self.peek() == '=' => tokens.push(self.scan_symbol(2)),
+                '<' if self.peek() == '<' => tokens.push(self.scan_symbol(2)),
                 '<' => tokens.push(self.scan_symbol(1)),
                 '>' if self.peek() == '=' => tokens.push(self.scan_symbol(2)),
+                '>' if self.peek() == '>' => tokens.push(self.scan_symbol(2)),
                 '>' => tokens.push(self.scan_symbol(1)),
-                '"' => tokens.push(self.scan_string()),
+                '"' => {
+                    let start = self.current;
+
+                    self.advance(); // skip the opening " char
+
+                    match self.scan_string_part(start, false) {
+                        Ok(token) => tokens.push(token),
+                        Err(error) => self.errors.push(error),
+                    }
+                }
                 '/' => match self.peek() {
                     '/' => self.skip_line_comment(),
                     '*' => self.skip_block_comment(),
                     _ => tokens.push(self.scan_symbol(1)),
                 },
-                '\n' => {
-                    self.line += 1;
-                    self.advance();
-                }
+                '\n' => self.advance(),
                 _ => {
-                    if c.is_digit(10) {
-                        tokens.push(self.scan_number());
+                    if c.is_ascii_digit() {
+                        match self.scan_number() {
+                            Ok(token) => tokens.push(token),
+                            Err(error) => self.errors.push(error),
+                        }
                     } else if self.is_alpha(c) {
                         tokens.push(self.scan_identifier());
                     } else {
-                        // TODO: Don't panic, output error message and keep scanning so we can report multiple errors at once.
-                        panic!("Unexpected character at line {}", self.line);
+                        self.advance();
+                        self.errors.push(ScanError {
+                            message: format!("Unexpected character '{c}'."),
+                            span: self.token_span(),
+                            help: None,
+                        });
                     }
                 }
             };
         }
 
-        tokens
+        tokens.push(Token::eof(self.position()));
+
+        (tokens, std::mem::take(&mut self.errors))
     }
     fn get_current_char(&self) -> char {
         self.source[self.current]
     }
+    // Every char goes through here, so this is the one place that keeps
+    // the line, column and byte offset bookkeeping in sync.
     fn advance(&mut self) {
         if self.is_not_at_end() {
+            let c = self.get_current_char();
+
             self.current += 1;
+            self.offset += c.len_utf8();
+
+            if c == '\n' {
+                self.line += 1;
+                self.line_start = self.current;
+            }
+        }
+    }
+    // An empty span sitting at the current position.
+    fn position(&self) -> Span {
+        Span {
+            line: self.line,
+            column: self.current - self.line_start + 1,
+            start: self.offset,
+            end: self.offset,
+        }
+    }
+    // Everything from the start of the current token up to the current position.
+    fn token_span(&self) -> Span {
+        Span {
+            end: self.offset,
+            ..self.start
         }
     }
     fn peek(&self) -> char {
@@ -109,63 +224,309 @@ impl Scanner {
         let lexeme_chars = &self.source[start..self.current];
         let lexeme = String::from_iter(lexeme_chars);
 
-        Token::get_token_from_symbol(&lexeme, self.line)
+        Token::symbol(&lexeme, self.token_span())
     }
     fn scan_identifier(&mut self) -> Token {
         let start = self.current;
 
-        while self.is_not_at_end() && self.get_current_char().is_alphanumeric() {
+        // same characters as `is_alpha` plus digits, so `a_b` is a single name
+        while self.is_not_at_end() {
+            let c = self.get_current_char();
+
+            if !(c.is_alphanumeric() || c == '_') {
+                break;
+            }
+
             self.advance();
         }
 
         let lexeme_chars = &self.source[start..self.current];
         let lexeme = String::from_iter(lexeme_chars);
 
-        Token::get_token_from_identifier(&lexeme, self.line)
+        Token::identifier(&lexeme, self.token_span())
     }
-    fn scan_number(&mut self) -> Token {
+    // Decimals like `1_000.5e-3`, or integers in hex, binary or octal with a
+    // `0x`, `0b` or `0o` prefix. Anything glued onto the end, like the second
+    // dot in `1.2.3`, makes the whole literal invalid.
+    fn scan_number(&mut self) -> Result<Token, ScanError> {
         let start = self.current;
 
-        while self.is_not_at_end() && self.get_current_char().is_digit(10) {
-            if self.peek().is_alphabetic() {
-                panic!("Invalid syntax at line {}", self.line);
+        let value = match (self.get_current_char(), self.peek()) {
+            ('0', 'x' | 'X') => self.scan_radix_number(16),
+            ('0', 'b' | 'B') => self.scan_radix_number(2),
+            ('0', 'o' | 'O') => self.scan_radix_number(8),
+            _ => self.scan_decimal_number(),
+        };
+
+        let mut trailing = false;
+
+        // skip the rest of the malformed literal so it isn't reported twice
+        while self.is_not_at_end() {
+            let c = self.get_current_char();
+
+            if c.is_alphanumeric() || c == '_' || (c == '.' && self.peek().is_alphanumeric()) {
+                trailing = true;
+                self.advance();
+            } else {
+                break;
             }
+        }
 
+        let lexeme = String::from_iter(&self.source[start..self.current]);
+
+        match value {
+            Ok(_) if trailing => Err(ScanError {
+                message: format!("Invalid number \"{lexeme}\"."),
+                span: self.token_span(),
+                help: None,
+            }),
+            Ok(value) => Ok(Token::number(&lexeme, value, self.token_span())),
+            Err((message, help)) => Err(ScanError {
+                message: message.to_owned(),
+                span: self.token_span(),
+                help: Some(help),
+            }),
+        }
+    }
+    fn scan_radix_number(&mut self, radix: u32) -> Result<f64, NumberError> {
+        self.advance(); // skip the 0
+        self.advance(); // and the x, b or o
+
+        let digits = self.scan_digits(radix).map_err(|error| {
+            error.explain((
+                "Expected digits after the number prefix.",
+                "0x takes hex digits, 0b binary digits and 0o octal digits",
+            ))
+        })?;
+
+        // folded straight into a float, so huge literals lose precision instead of overflowing
+        Ok(digits.chars().fold(0.0, |value, c| {
+            value * radix as f64 + c.to_digit(radix).unwrap_or_default() as f64
+        }))
+    }
+    fn scan_decimal_number(&mut self) -> Result<f64, NumberError> {
+        // the main loop only gets here on a digit, so there's at least one
+        let mut number = self
+            .scan_digits(10)
+            .map_err(|error| error.explain(("Expected digits.", "a number starts with a digit")))?;
+
+        if self.is_not_at_end() && self.get_current_char() == '.' {
             self.advance();
 
-            if self.is_not_at_end() && self.get_current_char() == '.' {
+            let fraction = self.scan_digits(10).map_err(|error| {
+                error.explain((
+                    "Expected digits after '.'.",
+                    "write the number as 1 or 1.0 instead of 1.",
+                ))
+            })?;
+
+            number = format!("{number}.{fraction}");
+        }
+
+        if self.is_not_at_end() && matches!(self.get_current_char(), 'e' | 'E') {
+            self.advance();
+
+            let mut sign = "";
+
+            if self.is_not_at_end() && matches!(self.get_current_char(), '+' | '-') {
+                sign = if self.get_current_char() == '-' {
+                    "-"
+                } else {
+                    ""
+                };
                 self.advance();
+            }
+
+            let exponent = self.scan_digits(10).map_err(|error| {
+                error.explain((
+                    "Expected digits in the exponent.",
+                    "an exponent looks like 1e10, 1e+10 or 1e-10",
+                ))
+            })?;
+
+            number = format!("{number}e{sign}{exponent}");
+        }
+
+        // only digits, a dot and an exponent made it into `number`, so this can't fail
+        Ok(number.parse().unwrap_or_default())
+    }
+    // Digits in the given radix with `_` separators allowed between them.
+    // Returns just the digits, and scans over misplaced separators before
+    // complaining so the error covers the whole literal.
+    fn scan_digits(&mut self, radix: u32) -> Result<String, DigitsError> {
+        let mut digits = String::new();
+        let mut misplaced = false;
+        let mut after_separator = false;
+
+        while self.is_not_at_end() {
+            let c = self.get_current_char();
+
+            if c == '_' {
+                misplaced |= digits.is_empty() || after_separator;
+                after_separator = true;
+            } else if c.is_digit(radix) {
+                digits.push(c);
+                after_separator = false;
+            } else {
+                break;
+            }
+
+            self.advance();
+        }
+
+        if digits.is_empty() {
+            return Err(DigitsError::Missing);
+        }
 
-                while self.get_current_char().is_digit(10) {
+        if misplaced || after_separator {
+            return Err(DigitsError::MisplacedSeparator);
+        }
+
+        Ok(digits)
+    }
+    // Scans a string up to its closing quote, or up to the next `${`, in which
+    // case an Interpolation token is returned and the embedded expression is
+    // scanned as ordinary tokens until the matching `}` hands control back here.
+    //
+    // The token's literal holds the string's value, with the quotes dropped and
+    // escape sequences replaced. Newlines are allowed, `advance` keeps count of them.
+    // A `resumed` part picks up after a `}` and gets a kind of its own, so the
+    // parser can't mistake it for the start of a new string.
+    fn scan_string_part(&mut self, start: usize, resumed: bool) -> Result<Token, ScanError> {
+        let mut value = String::new();
+
+        while self.is_not_at_end() && self.get_current_char() != '"' {
+            match self.get_current_char() {
+                '$' if self.peek() == '{' => {
+                    self.advance();
+                    self.advance();
+                    self.interpolations.push(0);
+
+                    let lexeme = String::from_iter(&self.source[start..self.current]);
+
+                    let kind = match resumed {
+                        true => TokenKind::InterpolationMiddle,
+                        false => TokenKind::Interpolation,
+                    };
+
+                    return Ok(Token::string(kind, &lexeme, value, self.token_span()));
+                }
+                '\\' => {
+                    // a bad escape is reported but the rest of the string is still scanned
+                    if let Some(c) = self.scan_escape() {
+                        value.push(c);
+                    }
+                }
+                c => {
+                    value.push(c);
                     self.advance();
                 }
             }
         }
 
-        let lexeme_chars = &self.source[start..self.current];
-        let lexeme = String::from_iter(lexeme_chars);
+        if !self.is_not_at_end() {
+            // inside `${...}` the quote is more likely meant to end the outer string
+            let help = match self.interpolations.is_empty() {
+                true => "add a closing '\"'",
+                false => "close the interpolated expression with '}' before ending the string",
+            };
+
+            return Err(ScanError {
+                message: "Unterminated string.".to_owned(),
+                span: self.token_span(),
+                help: Some(help),
+            });
+        }
+
+        self.advance(); // get rid of the trailing " char
+
+        let lexeme = String::from_iter(&self.source[start..self.current]);
 
-        Token::get_token_from_number(&lexeme, self.line)
+        let kind = match resumed {
+            true => TokenKind::InterpolationEnd,
+            false => TokenKind::String,
+        };
+
+        Ok(Token::string(kind, &lexeme, value, self.token_span()))
     }
-    fn scan_string(&mut self) -> Token {
-        let start = self.current;
+    // Scans one escape sequence, starting at its backslash.
+    fn scan_escape(&mut self) -> Option<char> {
+        let start = self.position();
 
-        self.advance(); // skip the first " char
+        self.advance(); // skip the \ char
 
-        while self.is_not_at_end() && self.get_current_char() != '"' && self.is_not_at_end() {
-            if self.peek() == '\n' {
-                panic!("Multiline strings are forbidden. Fix line {}", self.line);
-            }
+        // leave it to the caller to report the missing closing quote
+        if !self.is_not_at_end() {
+            return None;
+        }
+
+        let c = self.get_current_char();
+        let escaped = match c {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            '"' => Some('"'),
+            '\\' => Some('\\'),
+            // lets `\${` stand for a literal `${` instead of starting an interpolation
+            '$' => Some('$'),
+            'u' => return self.scan_unicode_escape(start),
+            _ => None,
+        };
+
+        self.advance();
+
+        if escaped.is_none() {
+            self.errors.push(ScanError {
+                message: format!("Invalid escape sequence '\\{}'.", c.escape_debug()),
+                span: Span {
+                    end: self.offset,
+                    ..start
+                },
+                help: Some("valid escapes are \\n, \\t, \\\", \\\\, \\$ and \\u{...}"),
+            });
+        }
 
+        escaped
+    }
+    // `\u{...}` with one to six hex digits naming a Unicode scalar value.
+    fn scan_unicode_escape(&mut self, start: Span) -> Option<char> {
+        self.advance(); // skip the u
+
+        let mut digits = String::new();
+        let mut closed = false;
+
+        if self.is_not_at_end() && self.get_current_char() == '{' {
             self.advance();
+
+            while self.is_not_at_end() && self.get_current_char().is_ascii_hexdigit() {
+                digits.push(self.get_current_char());
+                self.advance();
+            }
+
+            if self.is_not_at_end() && self.get_current_char() == '}' {
+                self.advance();
+                closed = true;
+            }
         }
 
-        self.advance(); // get rid of the trailing " char
+        let c = match closed && (1..=6).contains(&digits.len()) {
+            true => u32::from_str_radix(&digits, 16)
+                .ok()
+                .and_then(char::from_u32),
+            false => None,
+        };
 
-        let lexeme_chars = &self.source[start..self.current];
-        let lexeme = String::from_iter(lexeme_chars);
+        if c.is_none() {
+            self.errors.push(ScanError {
+                message: "Invalid unicode escape.".to_owned(),
+                span: Span {
+                    end: self.offset,
+                    ..start
+                },
+                help: Some("write it as \\u{...} with one to six hex digits, e.g. \\u{1F600}"),
+            });
+        }
 
-        Token::get_token_from_string(&lexeme, self.line)
+        c
     }
     fn skip_line_comment(&mut self) {
         while self.is_not_at_end() && self.get_current_char() != '\n' {
@@ -173,37 +534,130 @@ impl Scanner {
         }
     }
     fn skip_block_comment(&mut self) {
-        let comment_start_line = self.line;
-
-        // skip the "/*"
-        self.advance();
-        self.advance();
+        let mut depth = 0; // support for nested block comments
 
         while self.is_not_at_end() {
             match self.get_current_char() {
-                '\n' => {
-                    self.line += 1;
-                    self.advance();
-                }
-                '*' if self.peek() == '/' => {
+                '/' if self.peek() == '*' => {
+                    depth += 1;
                     self.advance();
                     self.advance();
-                    break;
                 }
-                '/' if self.peek() == '*' => {
+                '*' if self.peek() == '/' => {
+                    depth -= 1;
                     self.advance();
                     self.advance();
-                    self.skip_block_comment(); // support for nested block comments
+
+                    if depth == 0 {
+                        return;
+                    }
                 }
                 _ => self.advance(),
             };
-
-            if self.is_at_end() {
-                panic!(
-                    "Unterminated block comment started at line {}",
-                    comment_start_line
-                );
-            }
         }
+
+        self.errors.push(ScanError {
+            message: "Unterminated block comment.".to_owned(),
+            span: self.token_span(),
+            help: Some("every '/*' needs a matching '*/'"),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::TokenLiteral;
+
+    // Scans `source`, dropping the trailing Eof, along with the messages of
+    // any scan errors.
+    fn scan(source: &str) -> (Vec<Token>, Vec<String>) {
+        let (mut tokens, errors) = Scanner::from(source.to_owned()).scan_tokens();
+
+        tokens.pop();
+
+        (
+            tokens,
+            errors.into_iter().map(|error| error.message).collect(),
+        )
+    }
+
+    fn assert_number(source: &str, expected: f64) {
+        let (tokens, errors) = scan(source);
+
+        assert!(errors.is_empty(), "{source:?} failed to scan: {errors:?}");
+        assert_eq!(tokens.len(), 1, "{source:?}");
+        assert_eq!(tokens[0].literal, Some(TokenLiteral::Number(expected)));
+    }
+
+    fn assert_string(source: &str, expected: &str) {
+        let (tokens, errors) = scan(source);
+
+        assert!(errors.is_empty(), "{source:?} failed to scan: {errors:?}");
+        assert_eq!(tokens.len(), 1, "{source:?}");
+        assert_eq!(tokens[0].kind, TokenKind::String, "{source:?}");
+        assert_eq!(
+            tokens[0].literal,
+            Some(TokenLiteral::String(expected.to_owned()))
+        );
+    }
+
+    fn assert_error(source: &str, expected: &str) {
+        let (_, errors) = scan(source);
+
+        assert_eq!(errors, [expected], "{source:?}");
+    }
+
+    #[test]
+    fn separators_between_digits_are_skipped() {
+        assert_number("0x1_F", 31.0);
+        assert_number("1_000", 1000.0);
+    }
+
+    #[test]
+    fn rejects_misplaced_separators() {
+        assert_error("0b_1", "Misplaced digit separator.");
+        assert_error("1__0", "Misplaced digit separator.");
+    }
+
+    #[test]
+    fn rejects_missing_digits() {
+        assert_error("1.", "Expected digits after '.'.");
+        assert_error("1e", "Expected digits in the exponent.");
+    }
+
+    #[test]
+    fn scans_exponents() {
+        assert_number("1.5e-3", 0.0015);
+    }
+
+    #[test]
+    fn rejects_trailing_garbage_in_numbers() {
+        assert_error("123abc", "Invalid number \"123abc\".");
+        assert_error("666.666.666", "Invalid number \"666.666.666\".");
+    }
+
+    #[test]
+    fn scans_unicode_escapes() {
+        assert_string(r#""\u{1F600}""#, "😀");
+    }
+
+    #[test]
+    fn rejects_invalid_escapes() {
+        assert_error(r#""\q""#, "Invalid escape sequence '\\q'.");
+    }
+
+    #[test]
+    fn escaped_dollar_doesnt_start_an_interpolation() {
+        assert_string(r#""\${x}""#, "${x}");
+    }
+
+    #[test]
+    fn multi_line_strings_count_their_lines() {
+        let (tokens, errors) = scan("\"a\nb\nc\" x");
+
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(tokens[0].span.line, 1);
+        assert_eq!(tokens[1].span.line, 3);
     }
 }
@@ -0,0 +1,150 @@
+use crate::expr::{Expr, Variable};
+use crate::token::Token;
+use std::rc::Rc;
+
+pub trait StmtAcceptor {
+    fn accept<V: StmtVisitor>(&self, visitor: &V) -> V::Result;
+}
+
+pub trait StmtVisitor {
+    type Result;
+
+    fn visit_stmt(&self, stmt: &Stmt) -> Self::Result;
+    fn visit_block_stmt(&self, stmt: &Block) -> Self::Result;
+    fn visit_class_stmt(&self, stmt: &Class) -> Self::Result;
+    fn visit_expression_stmt(&self, stmt: &Expression) -> Self::Result;
+    fn visit_function_stmt(&self, stmt: &Rc<Function>) -> Self::Result;
+    fn visit_if_stmt(&self, stmt: &If) -> Self::Result;
+    fn visit_print_stmt(&self, stmt: &Print) -> Self::Result;
+    fn visit_return_stmt(&self, stmt: &Return) -> Self::Result;
+    fn visit_var_stmt(&self, stmt: &Var) -> Self::Result;
+    fn visit_while_stmt(&self, stmt: &While) -> Self::Result;
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    Block(Block),
+    Class(Class),
+    Expression(Expression),
+    // shared so that runtime function values can keep their declaration alive
+    Function(Rc<Function>),
+    If(Box<If>),
+    Print(Print),
+    Return(Return),
+    Var(Var),
+    While(Box<While>),
+}
+
+impl StmtAcceptor for Stmt {
+    fn accept<V: StmtVisitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_stmt(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct Block {
+    pub statements: Vec<Stmt>,
+}
+
+impl StmtAcceptor for Block {
+    fn accept<V: StmtVisitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_block_stmt(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: Token,
+    pub superclass: Option<Variable>,
+    pub methods: Vec<Rc<Function>>,
+}
+
+impl StmtAcceptor for Class {
+    fn accept<V: StmtVisitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_class_stmt(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct Expression {
+    pub expression: Expr,
+}
+
+impl StmtAcceptor for Expression {
+    fn accept<V: StmtVisitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_expression_stmt(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+}
+
+impl StmtAcceptor for Rc<Function> {
+    fn accept<V: StmtVisitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_function_stmt(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct If {
+    pub condition: Expr,
+    pub then_branch: Stmt,
+    pub else_branch: Option<Stmt>,
+}
+
+impl StmtAcceptor for If {
+    fn accept<V: StmtVisitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_if_stmt(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct Print {
+    pub expression: Expr,
+}
+
+impl StmtAcceptor for Print {
+    fn accept<V: StmtVisitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_print_stmt(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct Return {
+    pub keyword: Token,
+    pub value: Option<Expr>,
+}
+
+impl StmtAcceptor for Return {
+    fn accept<V: StmtVisitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_return_stmt(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct Var {
+    pub name: Token,
+    pub initializer: Option<Expr>,
+}
+
+impl StmtAcceptor for Var {
+    fn accept<V: StmtVisitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_var_stmt(self)
+    }
+}
+
+#[derive(Debug)]
+pub struct While {
+    pub condition: Expr,
+    pub body: Stmt,
+}
+
+impl StmtAcceptor for While {
+    fn accept<V: StmtVisitor>(&self, visitor: &V) -> V::Result {
+        visitor.visit_while_stmt(self)
+    }
+}
@@ -0,0 +1,113 @@
+use crate::callable::{LoxFunction, NativeFunction};
+use crate::class::{LoxClass, LoxInstance};
+use crate::map::LoxMap;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Clone)]
+pub enum Value {
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    Nil,
+    Function(Rc<LoxFunction>),
+    NativeFunction(Rc<NativeFunction>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    // shared, so every variable holding the list sees changes made through any of them
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<LoxMap>>),
+}
+
+impl Value {
+    // Lox follows Ruby's rule: false and nil are falsey, everything else is truthy
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Boolean(false) | Value::Nil)
+    }
+    // How the value reads inside a list or map. Strings keep their quotes so
+    // `["a, b"]` can't be mistaken for two elements.
+    pub fn quoted(&self) -> String {
+        match self {
+            Value::String(string) => format!("{string:?}"),
+            _ => self.to_string(),
+        }
+    }
+}
+
+// Functions, classes, instances, lists and maps are compared by identity, two values are only equal if they are the same object.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::Boolean(l), Value::Boolean(r)) => l == r,
+            (Value::Nil, Value::Nil) => true,
+            (Value::Function(l), Value::Function(r)) => Rc::ptr_eq(l, r),
+            (Value::NativeFunction(l), Value::NativeFunction(r)) => Rc::ptr_eq(l, r),
+            (Value::Class(l), Value::Class(r)) => Rc::ptr_eq(l, r),
+            (Value::Instance(l), Value::Instance(r)) => Rc::ptr_eq(l, r),
+            (Value::List(l), Value::List(r)) => Rc::ptr_eq(l, r),
+            (Value::Map(l), Value::Map(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{number}"),
+            Value::String(string) => write!(f, "{string}"),
+            Value::Boolean(boolean) => write!(f, "{boolean}"),
+            Value::Nil => write!(f, "nil"),
+            Value::Function(function) => write!(f, "{function}"),
+            Value::NativeFunction(function) => write!(f, "{function}"),
+            Value::Class(class) => write!(f, "{class}"),
+            Value::Instance(instance) => write!(f, "{}", instance.borrow()),
+            Value::List(list) => write_once(f, Rc::as_ptr(list).cast(), "[...]", |f| {
+                write!(f, "[")?;
+
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{}", element.quoted())?;
+                }
+
+                write!(f, "]")
+            }),
+            Value::Map(map) => write_once(f, Rc::as_ptr(map).cast(), "{...}", |f| {
+                write!(f, "{}", map.borrow())
+            }),
+        }
+    }
+}
+
+thread_local! {
+    // The collections being printed right now, innermost last.
+    static PRINTING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+// Writes a collection with `write`, unless it's already being printed further
+// up, like a list pushed onto itself. Then `placeholder` stands in for it,
+// since printing it again would never end.
+fn write_once(
+    f: &mut fmt::Formatter,
+    collection: *const (),
+    placeholder: &str,
+    write: impl FnOnce(&mut fmt::Formatter) -> fmt::Result,
+) -> fmt::Result {
+    if PRINTING.with_borrow(|printing| printing.contains(&collection)) {
+        return write!(f, "{placeholder}");
+    }
+
+    PRINTING.with_borrow_mut(|printing| printing.push(collection));
+
+    let result = write(f);
+
+    PRINTING.with_borrow_mut(|printing| printing.pop());
+
+    result
+}